@@ -28,4 +28,22 @@ enum RetCode extract_document(const char *document,
                               enum DescpType ty,
                               const char **out);
 
+/**
+ * Same as `extract_fragment`, with the nullable `base_url` to resolve the relative urls.
+ */
+enum RetCode extract_fragment_with(const char *fragment,
+                                   const struct ExtractOptCompiled *opt,
+                                   const char *base_url,
+                                   enum DescpType ty,
+                                   const char **out);
+
+/**
+ * Same as `extract_document`, with the nullable `base_url` to resolve the relative urls.
+ */
+enum RetCode extract_document_with(const char *document,
+                                   const struct ExtractOptCompiled *opt,
+                                   const char *base_url,
+                                   enum DescpType ty,
+                                   const char **out);
+
 void release_extract(char *ret);
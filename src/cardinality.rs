@@ -0,0 +1,60 @@
+use crate::one_or_list::OneOrList;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+/// The element to pick, `first`, `last`, `all` or the index from 0
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Pick {
+    Index(usize),
+    Name(String),
+}
+
+/// How many elements are extracted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cardinality {
+    /// One if exactly one is matched, otherwise a list
+    Auto,
+    /// The first one, or absent
+    First,
+    /// The last one, or absent
+    Last,
+    /// The one at the index, or absent
+    Nth(usize),
+    /// Always a list, possibly empty
+    Many,
+}
+
+impl Cardinality {
+    pub(crate) fn parse(many: Option<bool>, pick: Option<Pick>) -> Result<Self> {
+        let pick = match pick {
+            None => None,
+            Some(Pick::Index(index)) => Some(Self::Nth(index)),
+            Some(Pick::Name(name)) => Some(match name.as_str() {
+                "first" => Self::First,
+                "last" => Self::Last,
+                "all" => Self::Many,
+                name => bail!("unknown pick `{}`", name),
+            }),
+        };
+        Ok(match (many, pick) {
+            (None, None) => Self::Auto,
+            (Some(true), None) => Self::Many,
+            (Some(false), None) => Self::First,
+            (None, Some(pick)) => pick,
+            (Some(many), Some(pick)) if many == (pick == Self::Many) => pick,
+            (Some(_), Some(_)) => bail!("`many` conflicts with `pick`"),
+        })
+    }
+
+    /// Collect the extracted items, `None` if absent.
+    pub(crate) fn collect<T>(&self, mut values: Vec<T>) -> Option<OneOrList<T>> {
+        match self {
+            Self::Auto if values.len() == 1 => values.pop().map(OneOrList::One),
+            Self::Auto | Self::Many => Some(OneOrList::List(values)),
+            Self::First => values.into_iter().next().map(OneOrList::One),
+            Self::Last => values.pop().map(OneOrList::One),
+            Self::Nth(index) => values.into_iter().nth(*index).map(OneOrList::One),
+        }
+    }
+}
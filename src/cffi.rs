@@ -27,6 +27,11 @@ pub enum DescpType {
     Toml,
 }
 
+/// Compile the option described in JSON or TOML, released by `release_opt`.
+///
+/// # Safety
+///
+/// `descp` should be a valid NUL-terminated string.
 #[no_mangle]
 pub unsafe extern "C" fn compile_opt(
     descp: *const c_char,
@@ -43,6 +48,11 @@ pub unsafe extern "C" fn compile_opt(
     RetCode::Succ
 }
 
+/// Release the option compiled by `compile_opt`.
+///
+/// # Safety
+///
+/// `opt` should be returned by `compile_opt` and not be released yet.
 #[no_mangle]
 pub unsafe extern "C" fn release_opt(opt: *mut ExtractOptCompiled) {
     let _ = Box::from_raw(opt);
@@ -54,13 +64,23 @@ fn extract2c(extract: Extract, ty: DescpType) -> Result<*mut c_char, RetCode> {
         DescpType::Json => {
             serde_json::to_writer(&mut buf, &extract).map_err(|_| RetCode::InvalidArgs)?
         }
-        DescpType::Toml => buf = toml::to_vec(&extract).map_err(|_| RetCode::InvalidArgs)?,
+        DescpType::Toml => {
+            // the values are put before the tables, keeping the order of items
+            let value = toml::Value::try_from(&extract).map_err(|_| RetCode::InvalidArgs)?;
+            buf = toml::to_vec(&value).map_err(|_| RetCode::InvalidArgs)?
+        }
     };
     let c_extract = unsafe { CString::from_vec_unchecked(buf) };
 
     Ok(c_extract.into_raw())
 }
 
+/// Extract from the fragment, the result is released by `release_extract`.
+///
+/// # Safety
+///
+/// `fragment` should be a valid NUL-terminated string, and `opt` should be null or
+/// returned by `compile_opt`.
 #[no_mangle]
 pub unsafe extern "C" fn extract_fragment(
     fragment: *const c_char,
@@ -76,6 +96,12 @@ pub unsafe extern "C" fn extract_fragment(
     RetCode::Succ
 }
 
+/// Extract from the document, the result is released by `release_extract`.
+///
+/// # Safety
+///
+/// `document` should be a valid NUL-terminated string, and `opt` should be null or
+/// returned by `compile_opt`.
 #[no_mangle]
 pub unsafe extern "C" fn extract_document(
     document: *const c_char,
@@ -91,6 +117,68 @@ pub unsafe extern "C" fn extract_document(
     RetCode::Succ
 }
 
+unsafe fn context(base_url: *const c_char) -> Result<ExtractContext, RetCode> {
+    let mut ctx = ExtractContext::new();
+    if !base_url.is_null() {
+        let base_url = CStr::from_ptr(base_url)
+            .to_str()
+            .map_err(|_| RetCode::InvalidArgs)?;
+        ctx.base_url = Some(Url::parse(base_url).map_err(|_| RetCode::InvalidArgs)?);
+    }
+    Ok(ctx)
+}
+
+/// Same as `extract_fragment`, with the nullable `base_url` to resolve the relative urls.
+///
+/// # Safety
+///
+/// `fragment` should be a valid NUL-terminated string, `base_url` should be null or a valid
+/// NUL-terminated string, and `opt` should be null or returned by `compile_opt`.
+#[no_mangle]
+pub unsafe extern "C" fn extract_fragment_with(
+    fragment: *const c_char,
+    opt: *const ExtractOptCompiled,
+    base_url: *const c_char,
+    ty: DescpType,
+    out: &mut *const c_char,
+) -> RetCode {
+    let opt = throw!(opt.as_ref().ok_or(RetCode::InvalidArgs));
+    let fragment = throw!(CStr::from_ptr(fragment).to_str(), InvalidArgs);
+    let ctx = throw!(context(base_url));
+    let extract = super::extract_fragment_with(fragment, opt, &ctx);
+
+    *out = throw!(extract2c(extract, ty));
+    RetCode::Succ
+}
+
+/// Same as `extract_document`, with the nullable `base_url` to resolve the relative urls.
+///
+/// # Safety
+///
+/// `document` should be a valid NUL-terminated string, `base_url` should be null or a valid
+/// NUL-terminated string, and `opt` should be null or returned by `compile_opt`.
+#[no_mangle]
+pub unsafe extern "C" fn extract_document_with(
+    document: *const c_char,
+    opt: *const ExtractOptCompiled,
+    base_url: *const c_char,
+    ty: DescpType,
+    out: &mut *const c_char,
+) -> RetCode {
+    let opt = throw!(opt.as_ref().ok_or(RetCode::InvalidArgs));
+    let document = throw!(CStr::from_ptr(document).to_str(), InvalidArgs);
+    let ctx = throw!(context(base_url));
+    let extract = super::extract_document_with(document, opt, &ctx);
+
+    *out = throw!(extract2c(extract, ty));
+    RetCode::Succ
+}
+
+/// Release the result extracted.
+///
+/// # Safety
+///
+/// `ret` should be returned by the extracting functions and not be released yet.
 #[no_mangle]
 pub unsafe extern "C" fn release_extract(ret: *mut c_char) {
     let _ = CString::from_raw(ret);
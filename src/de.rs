@@ -0,0 +1,344 @@
+//! The `Deserializer` over the result extracted.
+
+use crate::one_or_list::OneOrList;
+use crate::{Extract, ExtractError, ExtractErrorKind, ExtractItem, ExtractText, Value};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::fmt;
+
+/// The error of deserializing, with the dotted path of option where it failed
+#[derive(Debug)]
+struct Error {
+    path: Option<String>,
+    reason: String,
+    /// The field missing, whose path is set by the struct
+    missing: Option<&'static str>,
+}
+
+impl Error {
+    /// Set the path if it's not set by the nested ones.
+    fn at(mut self, path: &str) -> Self {
+        self.path.get_or_insert_with(|| path.to_owned());
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error {
+            path: None,
+            reason: msg.to_string(),
+            missing: None,
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error {
+            missing: Some(field),
+            ..Error::custom(format!("missing field `{}`", field))
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Extract(&'a Extract),
+    Item(&'a ExtractItem),
+    Text(&'a ExtractText),
+    Value(&'a Value),
+}
+
+struct Deserializer<'a> {
+    node: Node<'a>,
+    path: String,
+}
+
+impl<'a> Deserializer<'a> {
+    /// Unwrap the single item, the item of only text and the single value.
+    fn narrow(&self) -> Node<'a> {
+        let mut node = self.node;
+        loop {
+            node = match node {
+                Node::Extract(OneOrList::One(item)) => Node::Item(item),
+                Node::Item(item) if item.items.is_empty() => match &item.text {
+                    Some(text) => Node::Text(text),
+                    None => return node,
+                },
+                Node::Text(OneOrList::One(value)) => Node::Value(value),
+                node => return node,
+            }
+        }
+    }
+
+    fn with(self, node: Node<'a>) -> Self {
+        Deserializer {
+            node,
+            path: self.path,
+        }
+    }
+}
+
+fn visit_value<'de, V: Visitor<'de>>(value: &'de Value, visitor: V) -> Result<V::Value, Error> {
+    match value {
+        Value::String(text) => visitor.visit_borrowed_str(text),
+        Value::Int(int) => visitor.visit_i64(*int),
+        Value::Float(float) => visitor.visit_f64(*float),
+        Value::Bool(boolean) => visitor.visit_bool(*boolean),
+        Value::Decimal(decimal) => visitor.visit_string(decimal.to_string()),
+        Value::Json(json) => {
+            de::Deserializer::deserialize_any(json, visitor).map_err(de::Error::custom)
+        }
+    }
+}
+
+struct Seq<I> {
+    iter: I,
+    path: String,
+}
+
+impl<'de, I: Iterator<Item = Node<'de>>> SeqAccess<'de> for Seq<I> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.iter.next() {
+            Some(node) => seed
+                .deserialize(Deserializer {
+                    node,
+                    path: self.path.clone(),
+                })
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The items of map, and the text keyed by `text` if exists.
+struct Map<'a> {
+    item: &'a ExtractItem,
+    index: usize,
+    path: String,
+    value: Option<(Node<'a>, String)>,
+}
+
+impl<'de> MapAccess<'de> for Map<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let item = self.item;
+        let (key, node) = match item.items.get_index(self.index) {
+            Some((key, extract)) => (key.as_str(), Node::Extract(extract)),
+            None => match &item.text {
+                Some(text) if self.index == item.items.len() => ("text", Node::Text(text)),
+                _ => return Ok(None),
+            },
+        };
+        self.index += 1;
+        let path = match (self.path.as_str(), node) {
+            (path, Node::Text(_)) => path.to_owned(),
+            ("", _) => key.to_owned(),
+            (path, _) => format!("{}.{}", path, key),
+        };
+        self.value = Some((node, path));
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (node, path) = self.value.take().expect("value is missing");
+        seed.deserialize(Deserializer {
+            node,
+            path: path.clone(),
+        })
+        .map_err(|e| e.at(&path))
+    }
+}
+
+macro_rules! parse_hint {
+    ($($method:ident => $visit:ident),* $(,)?) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self.narrow() {
+                Node::Value(Value::String(text)) => match text.trim().parse() {
+                    Ok(x) => visitor.$visit(x),
+                    Err(_) => visitor.visit_borrowed_str(text),
+                },
+                node => self.with(node).deserialize_any(visitor),
+            }
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.narrow() {
+            Node::Extract(OneOrList::List(items)) => visitor.visit_seq(Seq {
+                iter: items.iter().map(Node::Item),
+                path: self.path,
+            }),
+            Node::Item(item) if item.items.is_empty() && item.text.is_none() => {
+                visitor.visit_unit()
+            }
+            Node::Item(item) => visitor.visit_map(Map {
+                item,
+                index: 0,
+                path: self.path,
+                value: None,
+            }),
+            Node::Text(OneOrList::List(values)) => visitor.visit_seq(Seq {
+                iter: values.iter().map(Node::Value),
+                path: self.path,
+            }),
+            Node::Value(value) => visit_value(value, visitor),
+            Node::Extract(OneOrList::One(_)) | Node::Text(OneOrList::One(_)) => unreachable!(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.narrow() {
+            Node::Extract(OneOrList::List(items)) if items.is_empty() => visitor.visit_none(),
+            Node::Item(item) if item.items.is_empty() && item.text.is_none() => {
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    /// The single one is deserialized as the list of one.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.narrow() {
+            node @ (Node::Extract(OneOrList::List(_)) | Node::Text(OneOrList::List(_))) => {
+                self.with(node).deserialize_any(visitor)
+            }
+            node => visitor.visit_seq(Seq {
+                iter: std::iter::once(node),
+                path: self.path,
+            }),
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.narrow() {
+            Node::Item(item) => visitor.visit_map(Map {
+                item,
+                index: 0,
+                path: self.path,
+                value: None,
+            }),
+            node => self.with(node).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        // the text is keyed by `text` if the item also has the nested ones
+        let item = match self.node {
+            Node::Extract(OneOrList::One(item)) | Node::Item(item) => item,
+            Node::Extract(OneOrList::List(items)) if items.len() == 1 => &items[0],
+            Node::Extract(OneOrList::List(items)) => {
+                return Err(de::Error::custom(format!(
+                    "expected one item, found {}",
+                    items.len()
+                )))
+            }
+            _ => return self.deserialize_map(visitor),
+        };
+        let path = self.path.clone();
+        visitor
+            .visit_map(Map {
+                item,
+                index: 0,
+                path: self.path,
+                value: None,
+            })
+            .map_err(|e| match e.missing {
+                Some(field) if e.path.is_none() && path.is_empty() => e.at(field),
+                Some(field) if e.path.is_none() => e.at(&format!("{}.{}", path, field)),
+                _ => e,
+            })
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.narrow() {
+            Node::Value(Value::Int(int)) => visitor.visit_string(int.to_string()),
+            Node::Value(Value::Float(float)) => visitor.visit_string(float.to_string()),
+            Node::Value(Value::Bool(boolean)) => visitor.visit_string(boolean.to_string()),
+            node => self.with(node).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.narrow() {
+            Node::Value(Value::String(text)) => {
+                visitor.visit_enum(text.as_str().into_deserializer())
+            }
+            node => self.with(node).deserialize_any(visitor),
+        }
+    }
+
+    parse_hint! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char bytes byte_buf unit unit_struct tuple tuple_struct identifier ignored_any
+    }
+}
+
+/// Deserialize the result extracted, the error is reported with the path of option.
+pub(crate) fn from_extract<T: DeserializeOwned>(extract: &Extract) -> Result<T, ExtractError> {
+    let deserializer = Deserializer {
+        node: Node::Extract(extract),
+        path: String::new(),
+    };
+    T::deserialize(deserializer).map_err(|e| ExtractError {
+        path: e.path.unwrap_or_default(),
+        kind: ExtractErrorKind::Deserialize { reason: e.reason },
+    })
+}
@@ -0,0 +1,51 @@
+use crate::ValueType;
+use std::fmt;
+
+/// The error occurred while extracting
+#[derive(Debug)]
+pub struct ExtractError {
+    /// The dotted path of the option, e.g. `product.price`
+    pub path: String,
+    pub kind: ExtractErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ExtractErrorKind {
+    /// The text can not be converted into the required type
+    Convert {
+        text: String,
+        ty: ValueType,
+        reason: String,
+    },
+    /// The required element is not matched
+    Missing { selector: String },
+    /// The result can not be deserialized into the type required
+    Deserialize { reason: String },
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "<root>"
+        } else {
+            &self.path
+        };
+        match &self.kind {
+            ExtractErrorKind::Convert { text, ty, reason } => {
+                write!(
+                    f,
+                    "{}: can not convert `{}` into {}: {}",
+                    path, text, ty, reason
+                )
+            }
+            ExtractErrorKind::Missing { selector } => {
+                write!(f, "{}: required `{}` matched nothing", path, selector)
+            }
+            ExtractErrorKind::Deserialize { reason } => {
+                write!(f, "{}: can not deserialize: {}", path, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
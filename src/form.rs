@@ -0,0 +1,151 @@
+use crate::resolve;
+use crate::structured::{insert, json_item};
+use crate::{ExtractItem, State};
+use scraper::{ElementRef, Selector};
+use serde_json::{json, Map, Value as Json};
+
+fn text(elem: ElementRef) -> String {
+    elem.text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The options of `<select>` with the value, text and whether selected.
+fn options(select: ElementRef) -> Vec<(String, String, bool)> {
+    let selector = Selector::parse("option").unwrap();
+    select
+        .select(&selector)
+        .map(|x| {
+            let text = text(x);
+            let value = x
+                .value()
+                .attr("value")
+                .map_or_else(|| text.clone(), str::to_owned);
+            (value, text, x.value().attr("selected").is_some())
+        })
+        .collect()
+}
+
+/// The control with name, type, value and the state, and its value submitted if successful.
+fn control(elem: ElementRef) -> (Json, Option<Json>) {
+    let value = elem.value();
+    let disabled = value.attr("disabled").is_some();
+    let mut control = Map::new();
+    if let Some(name) = value.attr("name") {
+        control.insert("name".to_owned(), json!(name));
+    }
+    let submitted = match value.name() {
+        "select" => {
+            let multiple = value.attr("multiple").is_some();
+            let options = options(elem);
+            let mut selected: Vec<_> = options.iter().filter(|x| x.2).map(|x| json!(x.0)).collect();
+            if selected.is_empty() && !multiple {
+                selected.extend(options.first().map(|x| json!(x.0)));
+            }
+            let selected = if multiple {
+                Json::Array(selected)
+            } else {
+                selected.pop().unwrap_or(Json::Null)
+            };
+            let ty = if multiple {
+                "select-multiple"
+            } else {
+                "select-one"
+            };
+            control.insert("type".to_owned(), json!(ty));
+            control.insert("value".to_owned(), selected.clone());
+            control.insert(
+                "options".to_owned(),
+                options
+                    .into_iter()
+                    .map(|(value, text, selected)| {
+                        json!({ "value": value, "text": text, "selected": selected })
+                    })
+                    .collect(),
+            );
+            Some(selected)
+        }
+        "textarea" => {
+            let text = elem.text().collect::<String>();
+            control.insert("type".to_owned(), json!("textarea"));
+            control.insert("value".to_owned(), json!(text));
+            Some(json!(text))
+        }
+        name => {
+            let default = if name == "button" { "submit" } else { "text" };
+            let ty = value
+                .attr("type")
+                .unwrap_or(default)
+                .trim()
+                .to_ascii_lowercase();
+            let checkable = matches!(ty.as_str(), "checkbox" | "radio");
+            let input = value
+                .attr("value")
+                .unwrap_or(if checkable { "on" } else { "" });
+            control.insert("type".to_owned(), json!(ty));
+            control.insert("value".to_owned(), json!(input));
+            let checked = value.attr("checked").is_some();
+            if checkable {
+                control.insert("checked".to_owned(), json!(checked));
+            }
+            // the buttons are submitted only if they are clicked
+            match ty.as_str() {
+                "submit" | "reset" | "button" | "image" | "file" => None,
+                _ if checkable && !checked => None,
+                _ => Some(json!(input)),
+            }
+        }
+    };
+    if disabled {
+        control.insert("disabled".to_owned(), json!(true));
+    }
+    let submitted = submitted.filter(|x| !disabled && !x.is_null() && control.contains_key("name"));
+    (Json::Object(control), submitted)
+}
+
+/// The form with the resolved `action`, `method`, `enctype`, the `controls`,
+/// and the `payload` keyed by the names of controls submitted by default.
+pub(crate) fn extract_form(form: ElementRef, state: &State) -> ExtractItem {
+    let value = form.value();
+    let action = match (value.attr("action"), state.base_url.as_ref()) {
+        (Some(action), Some(base_url)) => resolve::resolve(base_url, action),
+        (Some(action), None) => action.trim().to_owned(),
+        (None, base_url) => base_url.map(|x| x.to_string()).unwrap_or_default(),
+    };
+    let method = value
+        .attr("method")
+        .unwrap_or("get")
+        .trim()
+        .to_ascii_lowercase();
+    let enctype = value
+        .attr("enctype")
+        .unwrap_or("application/x-www-form-urlencoded")
+        .trim()
+        .to_ascii_lowercase();
+
+    let selector = Selector::parse("input, select, textarea, button").unwrap();
+    let mut controls = vec![];
+    let mut payload = Map::new();
+    for elem in form.select(&selector) {
+        let (control, submitted) = control(elem);
+        if let Some(submitted) = submitted {
+            let name = elem.value().attr("name").unwrap();
+            let values = match submitted {
+                Json::Array(values) => values,
+                value => vec![value],
+            };
+            for value in values {
+                insert(&mut payload, name.to_owned(), value);
+            }
+        }
+        controls.push(control);
+    }
+    json_item(json!({
+        "action": action,
+        "method": method,
+        "enctype": enctype,
+        "controls": controls,
+        "payload": payload,
+    }))
+}
@@ -0,0 +1,16 @@
+use crate::{extract_document_into, ExtractError, ExtractOpt, ExtractOptCompiled};
+use serde::de::DeserializeOwned;
+
+/// The type extracted by the option built from its fields, implemented by `#[derive(Extract)]`.
+pub trait FromHtml: DeserializeOwned {
+    /// The option built from the attributes of type and fields
+    fn extract_opt() -> ExtractOpt;
+
+    /// The option compiled once
+    fn compiled() -> &'static ExtractOptCompiled;
+
+    /// Extract from a string of document.
+    fn extract(html: &str) -> Result<Self, ExtractError> {
+        extract_document_into(html, Self::compiled())
+    }
+}
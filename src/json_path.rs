@@ -0,0 +1,254 @@
+//! A subset of JSONPath, with the names, wildcards, indexes, slices and recursive descent.
+
+use anyhow::{bail, Result};
+use serde_json::Value as Json;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    /// `.name` or `['name', ...]`
+    Names(Vec<String>),
+    /// `.*` or `[*]`
+    Wildcard,
+    /// `[0, -1, ...]`, the negative ones are from the end
+    Indexes(Vec<i64>),
+    /// `[start:end]`
+    Slice(Option<i64>, Option<i64>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Child(Selector),
+    /// `..`, the selector is applied to the node and all its descendants
+    Descendant(Selector),
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn eat(&mut self, c: char) -> bool {
+        self.chars.next_if_eq(&c).is_some()
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.chars.next() {
+            Some(x) if x == c => Ok(()),
+            Some(x) => bail!("expect `{}` but found `{}`", c, x),
+            None => bail!("expect `{}` but found the end", c),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|x| x.is_whitespace()).is_some() {}
+    }
+
+    fn name(&mut self) -> Result<String> {
+        let mut name = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|x| x.is_alphanumeric() || matches!(x, '_' | '-' | '$' | '@'))
+        {
+            name.push(c);
+        }
+        if name.is_empty() {
+            bail!("expect a name");
+        }
+        Ok(name)
+    }
+
+    fn int(&mut self) -> Result<Option<i64>> {
+        let mut int = String::new();
+        if self.eat('-') {
+            int.push('-');
+        }
+        while let Some(c) = self.chars.next_if(char::is_ascii_digit) {
+            int.push(c);
+        }
+        match int.as_str() {
+            "" => Ok(None),
+            int => Ok(Some(int.parse()?)),
+        }
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            match self.chars.next() {
+                Some('\\') => match self.chars.next() {
+                    Some(c) => text.push(c),
+                    None => bail!("unterminated string"),
+                },
+                Some(c) if c == quote => return Ok(text),
+                Some(c) => text.push(c),
+                None => bail!("unterminated string"),
+            }
+        }
+    }
+
+    /// The selector after `.` or `..`
+    fn dotted(&mut self) -> Result<Selector> {
+        if self.eat('*') {
+            Ok(Selector::Wildcard)
+        } else if self.chars.peek() == Some(&'[') {
+            self.bracket()
+        } else {
+            Ok(Selector::Names(vec![self.name()?]))
+        }
+    }
+
+    fn bracket(&mut self) -> Result<Selector> {
+        self.expect('[')?;
+        self.skip_whitespace();
+        let selector = match self.chars.peek() {
+            Some('*') => {
+                self.chars.next();
+                Selector::Wildcard
+            }
+            Some(&quote) if quote == '\'' || quote == '"' => {
+                let mut names = vec![];
+                loop {
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(quote) if quote == '\'' || quote == '"' => {
+                            names.push(self.quoted(quote)?)
+                        }
+                        _ => bail!("expect a quoted name"),
+                    }
+                    self.skip_whitespace();
+                    if !self.eat(',') {
+                        break;
+                    }
+                }
+                Selector::Names(names)
+            }
+            _ => {
+                let start = self.int()?;
+                self.skip_whitespace();
+                if self.eat(':') {
+                    self.skip_whitespace();
+                    Selector::Slice(start, self.int()?)
+                } else {
+                    let mut indexes = vec![];
+                    let mut index = start;
+                    loop {
+                        match index {
+                            Some(x) => indexes.push(x),
+                            None => bail!("expect an index"),
+                        }
+                        self.skip_whitespace();
+                        if !self.eat(',') {
+                            break;
+                        }
+                        self.skip_whitespace();
+                        index = self.int()?;
+                    }
+                    Selector::Indexes(indexes)
+                }
+            }
+        };
+        self.skip_whitespace();
+        self.expect(']')?;
+        Ok(selector)
+    }
+}
+
+/// The index from the start of array, the negative one is from the end.
+fn index(len: usize, index: i64) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+fn select<'a>(node: &'a Json, selector: &Selector, out: &mut Vec<&'a Json>) {
+    match (selector, node) {
+        (Selector::Names(names), Json::Object(map)) => {
+            out.extend(names.iter().flat_map(|x| map.get(x)))
+        }
+        (Selector::Wildcard, Json::Object(map)) => out.extend(map.values()),
+        (Selector::Wildcard, Json::Array(values)) => out.extend(values),
+        (Selector::Indexes(indexes), Json::Array(values)) => out.extend(
+            indexes
+                .iter()
+                .flat_map(|x| index(values.len(), *x))
+                .map(|x| &values[x]),
+        ),
+        (Selector::Slice(start, end), Json::Array(values)) => {
+            let len = values.len();
+            let bound = |x: i64| {
+                if x < 0 {
+                    (len as i64 + x).max(0) as usize
+                } else {
+                    (x as usize).min(len)
+                }
+            };
+            let start = start.map_or(0, bound);
+            let end = end.map_or(len, bound);
+            if start < end {
+                out.extend(&values[start..end]);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn descendants<'a>(node: &'a Json, out: &mut Vec<&'a Json>) {
+    out.push(node);
+    match node {
+        Json::Object(map) => map.values().for_each(|x| descendants(x, out)),
+        Json::Array(values) => values.iter().for_each(|x| descendants(x, out)),
+        _ => {}
+    }
+}
+
+/// The compiled JSONPath, e.g. `$.product.offers[*].price`
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            chars: input.trim().chars().peekable(),
+        };
+        parser.expect('$')?;
+        let mut segments = vec![];
+        while let Some(&c) = parser.chars.peek() {
+            segments.push(match c {
+                '.' => {
+                    parser.chars.next();
+                    if parser.eat('.') {
+                        Segment::Descendant(parser.dotted()?)
+                    } else {
+                        Segment::Child(parser.dotted()?)
+                    }
+                }
+                '[' => Segment::Child(parser.bracket()?),
+                c => bail!("unexpected `{}` in json path `{}`", c, input),
+            });
+        }
+        Ok(JsonPath { segments })
+    }
+
+    /// Query the values matched in json.
+    pub fn query<'a>(&self, json: &'a Json) -> Vec<&'a Json> {
+        let mut nodes = vec![json];
+        for segment in self.segments.iter() {
+            let mut out = vec![];
+            for node in nodes {
+                match segment {
+                    Segment::Child(selector) => select(node, selector, &mut out),
+                    Segment::Descendant(selector) => {
+                        let mut all = vec![];
+                        descendants(node, &mut all);
+                        all.into_iter().for_each(|x| select(x, selector, &mut out));
+                    }
+                }
+            }
+            nodes = out;
+        }
+        nodes
+    }
+}
@@ -1,41 +1,358 @@
 //! A library to provide an easy way to extract data from HTML.
 
+mod cardinality;
 #[cfg(feature = "cffi")]
 pub mod cffi;
+mod de;
+mod error;
+mod form;
+mod from_html;
+mod json_path;
+mod links;
+mod markdown;
+mod meta;
 mod one_or_list;
+mod pattern;
+mod resolve;
+mod structured;
+mod table;
+mod text;
+mod transform;
+mod value;
+mod xpath;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+pub use cardinality::*;
+pub use error::*;
+pub use from_html::FromHtml;
+use indexmap::IndexMap;
+pub use json_path::JsonPath;
+pub use links::LinksOpt;
 use one_or_list::*;
-use regex::Regex;
+pub use pattern::*;
 use scraper::{ElementRef, Html, Selector};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+#[cfg(feature = "derive")]
+pub use sthe_derive::{sthe, Extract};
+pub use table::TableCompiled;
+pub use transform::*;
+pub use url::Url;
+pub use value::*;
+pub use xpath::XPath;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+}
+
+/// The kind of extracting
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// Extract the target of elements
+    #[default]
+    Element,
+    /// Extract the records keyed by header from `<table>`
+    Table,
+    /// Extract the documents of `<script type="application/ld+json">`
+    JsonLd,
+    /// Extract the items of `itemscope` and `itemprop`
+    Microdata,
+    /// Extract the items of RDFa `typeof` and `property`
+    Rdfa,
+    /// Extract the `<meta property="og:*">`, keyed without the prefix `og:`
+    Opengraph,
+    /// Extract the `<meta name="twitter:*">`, keyed without the prefix `twitter:`
+    TwitterCard,
+    /// Extract the action, method, enctype, controls and payload of `<form>`
+    Form,
+    /// Extract the links of `<a>`, `<link>` and `<area>`
+    Links,
+}
+
+/// How the text extracted is parsed for the nested options
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseAs {
+    /// Parse as the fragment of html
+    Html,
+}
+
+pub enum KindCompiled {
+    Element,
+    Table(TableCompiled),
+    JsonLd,
+    Microdata,
+    Rdfa,
+    Opengraph,
+    TwitterCard,
+    Form,
+    Links(LinksOpt),
+}
 
 /// The configurable option for extracting
 #[derive(Deserialize)]
 pub struct ExtractOpt {
+    /// One of `element`, `table`, `json_ld`, `microdata`, `rdfa`, `opengraph`, `twitter_card`, `form`
+    /// and `links`
+    #[serde(default)]
+    pub kind: Kind,
+    /// Rename the headers of `kind = "table"`, e.g. `{ "Product Name" = "name" }`
+    #[serde(default)]
+    pub table_rename: HashMap<String, String>,
+    /// The transforms of the columns of `kind = "table"`, keyed by the renamed header
+    #[serde(default)]
+    pub table_transform: HashMap<String, Vec<Transform>>,
+    /// Drop the links whose url appeared before, for `kind = "links"`
+    #[serde(default)]
+    pub links_dedup: bool,
+    /// Drop the fragment and sort the query of urls, for `kind = "links"`
+    #[serde(default)]
+    pub links_canonicalize: bool,
     #[serde(default)]
     pub target: OneOrList<String>,
-    pub selector: String,
+    /// The CSS selector, exclusive with `xpath`,
+    /// the alternatives in list are tried in order until any element is matched
+    #[serde(default)]
+    pub selector: Option<OneOrList<String>>,
+    /// The XPath selecting elements, exclusive with `selector`,
+    /// the alternatives in list are tried in order until any element is matched
+    #[serde(default)]
+    pub xpath: Option<OneOrList<String>>,
+    /// The separator joining the text nodes of `text` and `own_text`,
+    /// the text nodes are trimmed and the blank ones are skipped if given
+    #[serde(default)]
+    pub text_separator: Option<String>,
+    /// The steps to post-process the text of target
+    #[serde(default)]
+    pub transform: Vec<Transform>,
     #[serde(default)]
     pub regex: Option<String>,
-    #[serde(default, flatten)]
-    pub items: HashMap<String, ExtractOpt>,
+    /// One of `first`, `all`, `replace` and `filter`
+    #[serde(default)]
+    pub regex_mode: RegexMode,
+    /// The flags of `regex`, e.g. `["case_insensitive", "dot_all"]`
+    #[serde(default)]
+    pub regex_flags: Vec<RegexFlag>,
+    /// The replacement of mode `replace`, e.g. `$1-$2`
+    #[serde(default)]
+    pub regex_replace: Option<String>,
+    /// Parse the text as JSON and query by the JSONPath, e.g. `$.product.offers[*].price`
+    #[serde(default)]
+    pub json_path: Option<String>,
+    /// Parse the text as `html`, and evaluate the nested options against it
+    #[serde(default)]
+    pub parse_as: Option<ParseAs>,
+    /// Resolve the url attributes (`href`, `src`, `srcset`, ...) against the base url
+    #[serde(default)]
+    pub resolve_url: bool,
+    /// One of `string`, `int`, `float`, `bool`, `decimal`, `datetime` and `json`
+    #[serde(default, rename = "type")]
+    pub ty: Option<String>,
+    /// The format of `datetime`, e.g. `%Y-%m-%d %H:%M`
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Always extract a list if true, or the first one if false
+    #[serde(default)]
+    pub many: Option<bool>,
+    /// Pick `first`, `last`, `all` or the index from 0 of the elements
+    #[serde(default)]
+    pub pick: Option<Pick>,
+    /// Report an error by `try_extract_*` if no element is matched
+    #[serde(default)]
+    pub required: bool,
+    /// The text used if no element is matched
+    #[serde(default)]
+    pub default: Option<serde_json::Value>,
+    /// The nested options, in the sub-table `items` or `fields`,
+    /// or the other keys as the shorthand
+    #[serde(default, flatten, deserialize_with = "deserialize_items")]
+    pub items: IndexMap<String, ExtractOpt>,
+}
+
+/// Deserialize the nested options from `items`, `fields` or the keys left,
+/// the keys left are rejected if the sub-table is given or they are not tables.
+fn deserialize_items<'de, D>(
+    deserializer: D,
+) -> std::result::Result<IndexMap<String, ExtractOpt>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let mut rest = IndexMap::<String, serde_json::Value>::deserialize(deserializer)?;
+    let items = match (rest.shift_remove("items"), rest.shift_remove("fields")) {
+        (Some(_), Some(_)) => return Err(D::Error::custom("`items` and `fields` are exclusive")),
+        (Some(items), None) | (None, Some(items)) => {
+            if let Some(key) = rest.keys().next() {
+                return Err(D::Error::custom(format!(
+                    "unknown keyword `{}`, the nested options should be in `items` or `fields` if given",
+                    key
+                )));
+            }
+            match items {
+                serde_json::Value::Object(items) => items.into_iter().collect(),
+                _ => return Err(D::Error::custom("`items` or `fields` should be a table")),
+            }
+        }
+        (None, None) => rest,
+    };
+    items
+        .into_iter()
+        .map(|(key, value)| {
+            if !value.is_object() {
+                return Err(D::Error::custom(format!("unknown keyword `{}`", key)));
+            }
+            match serde_json::from_value(value) {
+                Ok(opt) => Ok((key, opt)),
+                Err(e) => Err(D::Error::custom(format!("in `{}`: {}", key, e))),
+            }
+        })
+        .collect()
 }
 
 pub struct ExtractOptCompiled {
+    pub kind: KindCompiled,
     pub target: OneOrList<String>,
-    pub selector: Selector,
-    pub regex: Option<Regex>,
-    pub items: HashMap<String, ExtractOptCompiled>,
+    /// The alternatives of selector
+    pub selector: Vec<SelectorCompiled>,
+    pub text_separator: Option<String>,
+    pub transform: Pipeline,
+    pub regex: Option<Pattern>,
+    pub json_path: Option<JsonPath>,
+    pub parse_as: Option<ParseAs>,
+    pub resolve_url: bool,
+    pub ty: Option<ValueType>,
+    pub cardinality: Cardinality,
+    pub required: bool,
+    pub default: Option<Value>,
+    pub items: IndexMap<String, ExtractOptCompiled>,
 }
 
 impl ExtractOpt {
     pub fn compile(self) -> Result<ExtractOptCompiled> {
+        if self.required && self.default.is_some() {
+            bail!("`required` conflicts with `default`");
+        }
+        let ty = self
+            .ty
+            .map(|x| ValueType::parse(&x, self.format))
+            .transpose()?;
+        let selector: Vec<_> = match (self.selector, self.xpath) {
+            (Some(selector), None) => selector
+                .into_vec()
+                .into_iter()
+                .map(|selector| {
+                    Ok(SelectorCompiled::Css {
+                        selector: Selector::parse(&selector).map_err(|e| anyhow!("{:?}", e))?,
+                        source: selector,
+                    })
+                })
+                .collect::<Result<_>>()?,
+            (None, Some(xpath)) => xpath
+                .into_vec()
+                .into_iter()
+                .map(|xpath| {
+                    Ok(SelectorCompiled::XPath {
+                        xpath: XPath::parse(&xpath)?,
+                        source: xpath,
+                    })
+                })
+                .collect::<Result<_>>()?,
+            (Some(_), Some(_)) => bail!("`selector` and `xpath` are exclusive"),
+            (None, None) => bail!("either `selector` or `xpath` is required"),
+        };
+        if selector.is_empty() {
+            bail!("the alternatives of selector should not be empty");
+        }
+        if self.kind != Kind::Element
+            && (!self.target.as_slice().is_empty()
+                || self.regex.is_some()
+                || !self.items.is_empty())
+        {
+            bail!("only kind `element` accepts `target`, `regex` or nested options");
+        }
+        if self.kind != Kind::Element
+            && (ty.is_some() || self.json_path.is_some() || self.text_separator.is_some())
+        {
+            bail!("only kind `element` accepts `type`, `json_path` or `text_separator`");
+        }
+        if !matches!(self.kind, Kind::Element | Kind::Table) && !self.transform.is_empty() {
+            bail!("only kind `element` and `table` accept `transform`");
+        }
+        if self.parse_as.is_some() && self.target.as_slice().is_empty() {
+            bail!("`parse_as` requires `target`");
+        }
+        let kind = match self.kind {
+            Kind::Table => KindCompiled::Table(TableCompiled::compile(
+                self.table_rename,
+                self.table_transform,
+            )?),
+            _ if !self.table_rename.is_empty() || !self.table_transform.is_empty() => {
+                bail!("`table_rename` and `table_transform` are only available for kind `table`")
+            }
+            Kind::Links => KindCompiled::Links(LinksOpt {
+                dedup: self.links_dedup,
+                canonicalize: self.links_canonicalize,
+            }),
+            _ if self.links_dedup || self.links_canonicalize => {
+                bail!("`links_dedup` and `links_canonicalize` are only available for kind `links`")
+            }
+            Kind::Element => KindCompiled::Element,
+            Kind::JsonLd => KindCompiled::JsonLd,
+            Kind::Microdata => KindCompiled::Microdata,
+            Kind::Rdfa => KindCompiled::Rdfa,
+            Kind::Opengraph => KindCompiled::Opengraph,
+            Kind::TwitterCard => KindCompiled::TwitterCard,
+            Kind::Form => KindCompiled::Form,
+        };
+        let regex = match self.regex {
+            Some(regex) => Some(Pattern::compile(
+                &regex,
+                self.regex_mode,
+                &self.regex_flags,
+                self.regex_replace,
+            )?),
+            None if self.regex_mode != RegexMode::First
+                || !self.regex_flags.is_empty()
+                || self.regex_replace.is_some() =>
+            {
+                bail!("`regex_mode`, `regex_flags` and `regex_replace` require `regex`")
+            }
+            None => None,
+        };
+        if let Some(name) = regex
+            .iter()
+            .flat_map(|x| x.regex.capture_names().flatten())
+            .find(|x| self.items.contains_key(*x))
+        {
+            bail!(
+                "the named capture `{}` conflicts with the nested option",
+                name
+            );
+        }
         Ok(ExtractOptCompiled {
+            kind,
             target: self.target,
-            selector: Selector::parse(&self.selector).map_err(|e| anyhow!("{:?}", e))?,
-            regex: self.regex.map(|x| Regex::new(&x)).transpose()?,
+            selector,
+            text_separator: self.text_separator,
+            transform: Pipeline::compile(self.transform)?,
+            regex,
+            json_path: self.json_path.map(|x| JsonPath::parse(&x)).transpose()?,
+            parse_as: self.parse_as,
+            resolve_url: self.resolve_url,
+            default: match (self.default.map(Value::from), ty.as_ref()) {
+                (Some(Value::String(text)), Some(ty)) => Some(
+                    ty.convert(&text)
+                        .map_err(|e| anyhow!("invalid default `{}`: {}", text, e))?,
+                ),
+                (default, _) => default,
+            },
+            ty,
+            cardinality: Cardinality::parse(self.many, self.pick)?,
+            required: self.required,
             items: self
                 .items
                 .into_iter()
@@ -45,84 +362,511 @@ impl ExtractOpt {
     }
 }
 
+/// The compiled selector
+pub enum SelectorCompiled {
+    Css { selector: Selector, source: String },
+    XPath { xpath: XPath, source: String },
+}
+
+impl SelectorCompiled {
+    /// Select by the first alternative which matches any element,
+    /// return the index of alternative and the elements.
+    fn select_any<'a>(
+        alternatives: &[Self],
+        elem: ElementRef<'a>,
+    ) -> Option<(usize, Vec<ElementRef<'a>>)> {
+        alternatives
+            .iter()
+            .map(|x| x.select(elem))
+            .enumerate()
+            .find(|(_, x)| !x.is_empty())
+    }
+
+    fn select<'a>(&self, elem: ElementRef<'a>) -> Vec<ElementRef<'a>> {
+        match self {
+            Self::Css { selector, .. } => elem.select(selector).collect(),
+            Self::XPath { xpath, .. } => xpath.select(elem),
+        }
+    }
+
+    /// The selector as it's written in option
+    pub fn source(&self) -> &str {
+        match self {
+            Self::Css { source, .. } | Self::XPath { source, .. } => source,
+        }
+    }
+}
+
 /// The text extracted
-pub type ExtractText = OneOrList<String>;
+pub type ExtractText = OneOrList<Value>;
 
 /// The item in result extracted
-#[derive(Serialize)]
+#[derive(Debug, Serialize)]
 pub struct ExtractItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     text: Option<ExtractText>,
-    #[serde(skip_serializing_if = "HashMap::is_empty", flatten)]
-    items: HashMap<String, Extract>,
+    #[serde(skip_serializing_if = "IndexMap::is_empty", flatten)]
+    items: IndexMap<String, Extract>,
 }
 
 /// The result extracted
 pub type Extract = OneOrList<ExtractItem>;
 
-fn extract_elem(elem: ElementRef, opt: &ExtractOptCompiled) -> Extract {
-    let select = elem.select(&opt.selector);
-    let mut extract_items = vec![];
-    for elem in select {
-        let text_list: Vec<_> = opt
-            .target
-            .as_slice()
-            .iter()
-            .flat_map(|target| match target.as_str() {
-                "html" => Some(elem.html()),
-                "inner_html" => Some(elem.inner_html()),
-                "text" => Some(elem.text().collect::<Vec<_>>().join("")),
-                attr => elem.value().attr(attr).map(|x| x.to_owned()),
+/// The context of extracting
+#[derive(Default, Clone)]
+pub struct ExtractContext {
+    /// The url which the relative urls are resolved against,
+    /// overridden by `<base href>` in the document
+    pub base_url: Option<Url>,
+}
+
+impl ExtractContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_base_url(mut self, base_url: Url) -> Self {
+        self.base_url = Some(base_url);
+        self
+    }
+}
+
+/// The report of extracting, for debugging the options
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    /// The times each alternative of selector matched first, keyed by the dotted path of option
+    pub selectors: HashMap<String, Vec<usize>>,
+    /// All errors occurred
+    pub errors: Vec<ExtractError>,
+}
+
+/// The state shared while extracting
+struct State {
+    base_url: Option<Url>,
+    path: Vec<String>,
+    report: ExtractReport,
+}
+
+impl State {
+    fn new(html: &Html, ctx: &ExtractContext) -> Self {
+        State {
+            base_url: resolve::base_url(html, ctx.base_url.as_ref()),
+            path: vec![],
+            report: ExtractReport::default(),
+        }
+    }
+
+    fn matched(&mut self, alternatives: usize, index: usize) {
+        let counts = self
+            .report
+            .selectors
+            .entry(self.path.join("."))
+            .or_insert_with(|| vec![0; alternatives]);
+        counts[index] += 1;
+    }
+
+    fn attr(&self, opt: &ExtractOptCompiled, attr: &str, value: &str) -> String {
+        match self.base_url.as_ref() {
+            Some(base_url) if opt.resolve_url => resolve::resolve_attr(base_url, attr, value),
+            _ => value.to_owned(),
+        }
+    }
+
+    fn error(&mut self, kind: ExtractErrorKind) {
+        self.report.errors.push(ExtractError {
+            path: self.path.join("."),
+            kind,
+        });
+    }
+
+    fn convert(&mut self, ty: Option<&ValueType>, text: String) -> Option<Value> {
+        let ty = match ty {
+            Some(ty) => ty,
+            None => return Some(Value::String(text)),
+        };
+        match ty.convert(&text) {
+            Ok(value) => Some(value),
+            Err(reason) => {
+                self.error(ExtractErrorKind::Convert {
+                    text,
+                    ty: ty.clone(),
+                    reason,
+                });
+                None
+            }
+        }
+    }
+
+    /// Query the text parsed as JSON, the scalars are converted by type if given.
+    fn query(&mut self, json_path: &JsonPath, ty: Option<&ValueType>, text: String) -> Vec<Value> {
+        let json = match serde_json::from_str(&text) {
+            Ok(json) => json,
+            Err(e) => {
+                self.error(ExtractErrorKind::Convert {
+                    text,
+                    ty: ValueType::Json,
+                    reason: format!("{}", e),
+                });
+                return vec![];
+            }
+        };
+        json_path
+            .query(&json)
+            .into_iter()
+            .flat_map(without_null)
+            .flat_map(|value| match (ty, &value) {
+                (None, _) => Some(Value::from(value)),
+                (Some(_), serde_json::Value::String(text)) => self.convert(ty, text.clone()),
+                (Some(_), value) => self.convert(ty, value.to_string()),
             })
-            .flat_map(|text| {
-                Some(if let Some(regex) = opt.regex.as_ref() {
-                    regex
-                        .captures(&text.trim())?
-                        .iter()
-                        .skip(1)
-                        .flat_map(|x| x.map(|x| x.as_str().to_owned()))
-                        .collect()
-                } else {
-                    vec![text.trim().to_owned()]
+            .collect()
+    }
+}
+
+/// Drop the nulls in json, which can not be serialized as TOML.
+fn without_null(value: &serde_json::Value) -> Option<serde_json::Value> {
+    use serde_json::Value as Json;
+    match value {
+        Json::Null => None,
+        Json::Array(values) => Some(Json::Array(values.iter().flat_map(without_null).collect())),
+        Json::Object(map) => Some(Json::Object(
+            map.iter()
+                .flat_map(|(k, v)| Some((k.clone(), without_null(v)?)))
+                .collect(),
+        )),
+        value => Some(value.clone()),
+    }
+}
+
+/// Collect the text of an item, one value or a list of them.
+fn collect_text(mut values: Vec<Value>) -> Option<ExtractText> {
+    match values.len() {
+        0 => None,
+        1 => values.pop().map(ExtractText::One),
+        _ => Some(ExtractText::List(values)),
+    }
+}
+
+/// Extract from the element, `None` if the result is absent.
+fn extract_elem(elem: ElementRef, opt: &ExtractOptCompiled, state: &mut State) -> Option<Extract> {
+    let select = match SelectorCompiled::select_any(&opt.selector, elem) {
+        Some((index, select)) => {
+            state.matched(opt.selector.len(), index);
+            select
+        }
+        None => vec![],
+    };
+    let mut extract_items = vec![];
+    let separator = opt.text_separator.as_deref();
+    for (index, elem) in select.into_iter().enumerate() {
+        match &opt.kind {
+            KindCompiled::Element => {}
+            KindCompiled::Table(table) => {
+                extract_items.extend(table::extract_table(elem, table, &opt.transform));
+                continue;
+            }
+            KindCompiled::JsonLd => {
+                extract_items.extend(structured::json_ld(elem, state));
+                continue;
+            }
+            KindCompiled::Microdata => {
+                extract_items.extend(structured::microdata(elem, opt, state));
+                continue;
+            }
+            KindCompiled::Rdfa => {
+                extract_items.extend(structured::rdfa(elem, opt, state));
+                continue;
+            }
+            KindCompiled::Opengraph => {
+                let attrs = ["property", "name"];
+                extract_items.push(structured::meta(elem, opt, state, &attrs, "og:"));
+                continue;
+            }
+            KindCompiled::TwitterCard => {
+                let attrs = ["name", "property"];
+                extract_items.push(structured::meta(elem, opt, state, &attrs, "twitter:"));
+                continue;
+            }
+            KindCompiled::Form => {
+                extract_items.push(form::extract_form(elem, state));
+                continue;
+            }
+            KindCompiled::Links(links) => {
+                extract_items.extend(links::extract_links(elem, links, state));
+                continue;
+            }
+        }
+        let mut target_list = vec![];
+        // the attributes matched by the wildcard targets, `@*` or the prefix like `data-*`
+        let mut attr_list = vec![];
+        for target in opt.target.as_slice() {
+            match target.as_str() {
+                "html" => target_list.push(elem.html()),
+                "inner_html" => target_list.push(elem.inner_html()),
+                "text" => target_list.push(text::join_text(elem.text(), separator)),
+                "own_text" => target_list.push(text::join_text(text::own_text(elem), separator)),
+                "inner_text" => target_list.push(text::inner_text(elem)),
+                "markdown" => target_list.push(markdown::markdown(elem, state.base_url.as_ref())),
+                "tag" => target_list.push(elem.value().name().to_owned()),
+                "css_path" => target_list.push(meta::css_path(elem)),
+                "index" => target_list.push(index.to_string()),
+                "sibling_index" => target_list.push(meta::sibling_index(elem).to_string()),
+                "depth" => target_list.push(meta::depth(elem).to_string()),
+                "ancestors" => target_list.push(meta::ancestors(elem)),
+                wildcard if wildcard.ends_with('*') => {
+                    let prefix = wildcard.trim_start_matches('@').trim_end_matches('*');
+                    attr_list.extend(
+                        elem.value()
+                            .attrs()
+                            .filter(|(attr, _)| attr.starts_with(prefix))
+                            .map(|(attr, value)| (attr, state.attr(opt, attr, value))),
+                    );
+                }
+                attr => {
+                    target_list.extend(elem.value().attr(attr).map(|x| state.attr(opt, attr, x)))
+                }
+            }
+        }
+        let mut text_list = vec![];
+        let mut named_list = vec![];
+        let mut matched = false;
+        for text in opt.transform.apply(target_list) {
+            match opt.regex.as_ref() {
+                Some(regex) => matched |= regex.apply(text, &mut text_list, &mut named_list),
+                None => text_list.push(text),
+            }
+        }
+        for (attr, value) in attr_list {
+            named_list.extend(
+                opt.transform
+                    .apply(vec![value])
+                    .into_iter()
+                    .map(|x| (attr, x)),
+            );
+        }
+        if !matched
+            && matches!(
+                opt.regex,
+                Some(Pattern {
+                    mode: PatternMode::Filter,
+                    ..
                 })
-            })
-            .flatten()
-            .collect();
-        let text = match text_list.len() {
-            0 => None,
-            1 => Some(ExtractText::One(text_list.into_iter().next().unwrap())),
-            _ => Some(ExtractText::List(text_list)),
+            )
+        {
+            continue;
+        }
+        let fragment = opt
+            .parse_as
+            .map(|ParseAs::Html| Html::parse_fragment(&text_list.join("")));
+        let text_list: Vec<_> = match opt.json_path.as_ref() {
+            Some(json_path) => text_list
+                .into_iter()
+                .flat_map(|text| state.query(json_path, opt.ty.as_ref(), text))
+                .collect(),
+            None => text_list
+                .into_iter()
+                .flat_map(|text| state.convert(opt.ty.as_ref(), text))
+                .collect(),
         };
-        let items: HashMap<_, _> = opt
-            .items
-            .iter()
-            .map(|(k, v)| (k.clone(), extract_elem(elem, v)))
+        let text = if text_list.is_empty() && opt.target.as_slice().iter().all(|x| x.ends_with('*'))
+        {
+            None
+        } else {
+            collect_text(text_list)
+        };
+
+        let mut named: IndexMap<String, Vec<Value>> = IndexMap::new();
+        for (name, text) in named_list {
+            state.path.push(name.to_owned());
+            if let Some(value) = state.convert(opt.ty.as_ref(), text) {
+                named.entry(name.to_owned()).or_default().push(value);
+            }
+            state.path.pop();
+        }
+        let mut items: IndexMap<_, _> = named
+            .into_iter()
+            .map(|(k, v)| {
+                let item = ExtractItem {
+                    text: collect_text(v),
+                    items: IndexMap::new(),
+                };
+                (k, Extract::One(item))
+            })
             .collect();
+        let root = fragment.as_ref().map_or(elem, Html::root_element);
+        for (k, v) in opt.items.iter() {
+            state.path.push(k.clone());
+            if let Some(extract) = extract_elem(root, v, state) {
+                items.insert(k.clone(), extract);
+            }
+            state.path.pop();
+        }
         extract_items.push(ExtractItem { text, items });
     }
 
-    if extract_items.len() == 1 {
-        Extract::One(extract_items.into_iter().next().unwrap())
-    } else {
-        Extract::List(extract_items)
+    if extract_items.is_empty() {
+        if let Some(default) = opt.default.as_ref() {
+            extract_items.push(ExtractItem {
+                text: collect_text(vec![default.clone()]),
+                items: IndexMap::new(),
+            });
+        } else {
+            missing(opt, state);
+        }
     }
+
+    opt.cardinality.collect(extract_items)
 }
 
-fn extract_html(html: Html, opt: &ExtractOptCompiled) -> Extract {
+/// Report the required option matching nothing, or the required ones nested in it which have
+/// nothing to match.
+fn missing(opt: &ExtractOptCompiled, state: &mut State) {
+    if opt.required {
+        state.error(ExtractErrorKind::Missing {
+            selector: opt
+                .selector
+                .iter()
+                .map(SelectorCompiled::source)
+                .collect::<Vec<_>>()
+                .join(" || "),
+        });
+        return;
+    }
+    for (k, v) in opt.items.iter() {
+        state.path.push(k.clone());
+        missing(v, state);
+        state.path.pop();
+    }
+}
+
+fn extract_html(
+    html: Html,
+    opt: &ExtractOptCompiled,
+    ctx: &ExtractContext,
+) -> (Extract, ExtractReport) {
+    let mut state = State::new(&html, ctx);
     let root_elem = html.root_element();
-    extract_elem(root_elem, opt)
+    let extract = extract_elem(root_elem, opt, &mut state).unwrap_or(Extract::List(vec![]));
+    (extract, state.report)
+}
+
+fn first_error(
+    (extract, report): (Extract, ExtractReport),
+) -> std::result::Result<Extract, ExtractError> {
+    match report.errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(extract),
+    }
 }
 
 /// Extract from a string of document.
+///
+/// The text failed to be converted into the required type is dropped,
+/// see [`try_extract_document`] to catch the failure.
 pub fn extract_document(document: &str, opt: &ExtractOptCompiled) -> Extract {
-    let document = Html::parse_document(document);
-    extract_html(document, opt)
+    extract_document_with(document, opt, &ExtractContext::default())
 }
 
 /// Extract from a string of fragment.
+///
+/// The text failed to be converted into the required type is dropped,
+/// see [`try_extract_fragment`] to catch the failure.
 pub fn extract_fragment(fragment: &str, opt: &ExtractOptCompiled) -> Extract {
+    extract_fragment_with(fragment, opt, &ExtractContext::default())
+}
+
+/// Extract from a string of document with the context.
+pub fn extract_document_with(
+    document: &str,
+    opt: &ExtractOptCompiled,
+    ctx: &ExtractContext,
+) -> Extract {
+    let document = Html::parse_document(document);
+    extract_html(document, opt, ctx).0
+}
+
+/// Extract from a string of fragment with the context.
+pub fn extract_fragment_with(
+    fragment: &str,
+    opt: &ExtractOptCompiled,
+    ctx: &ExtractContext,
+) -> Extract {
     let fragment = Html::parse_fragment(fragment);
-    extract_html(fragment, opt)
+    extract_html(fragment, opt, ctx).0
+}
+
+/// Extract from a string of document and deserialize the result into `T`,
+/// failed on the first error with the path of option.
+pub fn extract_document_into<T: DeserializeOwned>(
+    document: &str,
+    opt: &ExtractOptCompiled,
+) -> std::result::Result<T, ExtractError> {
+    de::from_extract(&try_extract_document(document, opt)?)
+}
+
+/// Extract from a string of fragment and deserialize the result into `T`,
+/// failed on the first error with the path of option.
+pub fn extract_fragment_into<T: DeserializeOwned>(
+    fragment: &str,
+    opt: &ExtractOptCompiled,
+) -> std::result::Result<T, ExtractError> {
+    de::from_extract(&try_extract_fragment(fragment, opt)?)
+}
+
+/// Extract from a string of document, failed on the first error.
+pub fn try_extract_document(
+    document: &str,
+    opt: &ExtractOptCompiled,
+) -> std::result::Result<Extract, ExtractError> {
+    try_extract_document_with(document, opt, &ExtractContext::default())
+}
+
+/// Extract from a string of fragment, failed on the first error.
+pub fn try_extract_fragment(
+    fragment: &str,
+    opt: &ExtractOptCompiled,
+) -> std::result::Result<Extract, ExtractError> {
+    try_extract_fragment_with(fragment, opt, &ExtractContext::default())
+}
+
+/// Extract from a string of document with the context, failed on the first error.
+pub fn try_extract_document_with(
+    document: &str,
+    opt: &ExtractOptCompiled,
+    ctx: &ExtractContext,
+) -> std::result::Result<Extract, ExtractError> {
+    let document = Html::parse_document(document);
+    first_error(extract_html(document, opt, ctx))
+}
+
+/// Extract from a string of fragment with the context, failed on the first error.
+pub fn try_extract_fragment_with(
+    fragment: &str,
+    opt: &ExtractOptCompiled,
+    ctx: &ExtractContext,
+) -> std::result::Result<Extract, ExtractError> {
+    let fragment = Html::parse_fragment(fragment);
+    first_error(extract_html(fragment, opt, ctx))
+}
+
+/// Extract from a string of document with the context, and report for debugging.
+pub fn extract_document_report(
+    document: &str,
+    opt: &ExtractOptCompiled,
+    ctx: &ExtractContext,
+) -> (Extract, ExtractReport) {
+    let document = Html::parse_document(document);
+    extract_html(document, opt, ctx)
+}
+
+/// Extract from a string of fragment with the context, and report for debugging.
+pub fn extract_fragment_report(
+    fragment: &str,
+    opt: &ExtractOptCompiled,
+    ctx: &ExtractContext,
+) -> (Extract, ExtractReport) {
+    let fragment = Html::parse_fragment(fragment);
+    extract_html(fragment, opt, ctx)
 }
 
 #[cfg(test)]
@@ -212,4 +956,1053 @@ mod tests {
             "#
         };
     }
+
+    #[test]
+    fn test_named_capture() {
+        test_case! {
+            html: r#"
+<div class="parent"> Hello, <h2>world!</h2> </div>
+            "#,
+            opt: r#"
+                target = "text"
+                selector = ".parent"
+                regex = "(?P<greeting>.*?), (?P<name>.*?)(!)"
+            "#,
+            expect: r#"
+                text = "!"
+                [greeting]
+                text = "Hello"
+                [name]
+                text = "world"
+            "#
+        };
+
+        let opt: ExtractOpt = toml::from_str(
+            r#"
+                selector = ".parent"
+                regex = "(?P<name>.*)"
+                [name]
+                selector = "h2"
+            "#,
+        )
+        .unwrap();
+        assert!(opt.compile().is_err());
+    }
+
+    #[test]
+    fn test_regex_mode() {
+        test_case! {
+            html: r#"
+<ul>
+    <li>Apple: 1, Banana: 2</li>
+    <li>apple pie</li>
+    <li>Cherry</li>
+</ul>
+            "#,
+            opt: r#"
+                selector = "ul"
+
+                [all]
+                target = "text"
+                selector = "li:first-child"
+                regex = "(\\w+): \\d"
+                regex_mode = "all"
+
+                [replace]
+                target = "text"
+                selector = "li:first-child"
+                regex = "(\\w+): (\\d)"
+                regex_mode = "replace"
+                regex_replace = "$2 $1"
+
+                [filter]
+                target = "text"
+                selector = "li"
+                regex = "^apple"
+                regex_mode = "filter"
+                regex_flags = ["case_insensitive"]
+          
+                [whole]
+                target = "text"
+                selector = "li:first-child"
+                regex = "\\d"
+                regex_mode = "all"
+            "#,
+            expect: r#"
+                [all]
+                text = ["Apple", "Banana"]
+                [replace]
+                text = "1 Apple, 2 Banana"
+                [[filter]]
+                text = "Apple: 1, Banana: 2"
+                [[filter]]
+                text = "apple pie"
+                [whole]
+                text = ["1", "2"]
+            "#
+        };
+    }
+
+    #[test]
+    fn test_cardinality() {
+        test_case! {
+            html: r#"
+<ul>
+    <li>1</li>
+    <li>2</li>
+    <li class="c">3</li>
+</ul>
+            "#,
+            opt: r#"
+                selector = "ul"
+
+                [every]
+                target = "text"
+                selector = "li:first-child"
+                many = true
+
+                [first]
+                target = "text"
+                selector = "li"
+                many = false
+
+                [last]
+                target = "text"
+                selector = "li"
+                pick = "last"
+
+                [second]
+                target = "text"
+                selector = "li"
+                pick = 1
+
+                [both]
+                target = ["text", "class"]
+                selector = "li"
+                pick = "last"
+
+                [absent]
+                target = "text"
+                selector = "p"
+                many = false
+
+                [empty]
+                target = "text"
+                selector = "p"
+                pick = "all"
+            "#,
+            expect: r#"
+                empty = []
+                [[every]]
+                text = "1"
+                [first]
+                text = "1"
+                [last]
+                text = "3"
+                [second]
+                text = "2"
+                [both]
+                text = ["3", "c"]
+            "#
+        };
+    }
+
+    #[test]
+    fn test_fallback_selector() {
+        let opt: ExtractOpt = toml::from_str(
+            r#"
+                selector = "div"
+
+                [title]
+                target = "text"
+                selector = ["h1.title", "h2", "h3"]
+            "#,
+        )
+        .unwrap();
+        let (extract, report) = extract_fragment_report(
+            "<div><h2>A</h2><h3>B</h3></div>",
+            &opt.compile().unwrap(),
+            &ExtractContext::default(),
+        );
+        let extract_value = toml::Value::try_from(extract).unwrap();
+        let expect_value = toml::from_str(
+            r#"
+                [title]
+                text = "A"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(extract_value, expect_value);
+        assert_eq!(report.selectors["title"], vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn test_table() {
+        test_case! {
+            html: r#"
+<div>
+<table>
+    <thead>
+        <tr><th>Product Name</th><th colspan="2">Price</th></tr>
+    </thead>
+    <tbody>
+        <tr><td rowspan="2">Apple</td><td>$1</td><td>$2</td></tr>
+        <tr><td>$3</td><td>$4</td></tr>
+    </tbody>
+</table>
+<table class="plain">
+    <tr></tr>
+    <tr><td>Name</td><td>Qty</td></tr>
+    <tr><td>Pear</td><td>2</td></tr>
+</table>
+</div>
+            "#,
+            opt: r#"
+                selector = "div"
+
+                [table]
+                selector = "table:not(.plain)"
+                kind = "table"
+
+                [table.table_rename]
+                "Product Name" = "name"
+
+                [table.table_transform]
+                Price = [{ strip_prefix = "$" }]
+
+                [plain]
+                selector = "table.plain"
+                kind = "table"
+            "#,
+            expect: r#"
+                [[table]]
+                name = { text = "Apple" }
+                Price = { text = "1" }
+                Price_2 = { text = "2" }
+                [[table]]
+                name = { text = "Apple" }
+                Price = { text = "3" }
+                Price_2 = { text = "4" }
+                [plain]
+                Name = { text = "Pear" }
+                Qty = { text = "2" }
+            "#
+        };
+    }
+
+    #[test]
+    fn test_inner_text() {
+        test_case! {
+            html: r#"
+<div class="parent"><p>a</p><p>b <i>c</i>
+    d</p>x<br>y<script>z</script><pre> 1
+  2</pre></div>
+            "#,
+            opt: r#"
+                target = "inner_text"
+                selector = ".parent"
+            "#,
+            expect: r#"
+                text = "a\n\nb c d\n\nx\ny\n 1\n  2"
+            "#
+        };
+    }
+
+    #[test]
+    fn test_own_text() {
+        test_case! {
+            html: r#"
+<ul>
+    <li>Price <span>$5</span></li>
+    <li><b>a</b> <b>b</b> c </li>
+</ul>
+            "#,
+            opt: r#"
+                selector = "ul"
+
+                [own]
+                target = "own_text"
+                selector = "li:first-child"
+
+                [separated]
+                target = "text"
+                selector = "li:last-child"
+                text_separator = ", "
+            "#,
+            expect: r#"
+                [own]
+                text = "Price"
+                [separated]
+                text = "a, b, c"
+            "#
+        };
+    }
+
+    #[test]
+    fn test_markdown() {
+        let opt: ExtractOpt = toml::from_str(
+            r#"
+                target = "markdown"
+                selector = "article"
+            "#,
+        )
+        .unwrap();
+        let ctx = ExtractContext::new().with_base_url(Url::parse("https://x.com/a/").unwrap());
+        let extract = extract_fragment_with(
+            r#"
+<article>
+    <h2>Title <em>here</em></h2>
+    <p>Some <strong>bold</strong> and a <a href="p/1">link</a>.<br>Next_line</p>
+    <ul>
+        <li>one</li>
+        <li>two<ol><li>nested</li></ol></li>
+    </ul>
+    <pre><code class="language-rust">fn main() {}
+</code></pre>
+    <table>
+        <tr><th>Name</th><th>Price</th></tr>
+        <tr><td>a|b</td><td><code>5</code></td></tr>
+    </table>
+    <script>ignored()</script>
+</article>
+            "#,
+            &opt.compile().unwrap(),
+            &ctx,
+        );
+        let extract_value = toml::Value::try_from(extract).unwrap();
+        let expect_value = toml::from_str(
+            r#"
+                text = '''
+## Title *here*
+
+Some **bold** and a [link](https://x.com/a/p/1).\
+Next\_line
+
+- one
+- two
+  1. nested
+
+```rust
+fn main() {}
+```
+
+| Name | Price |
+| --- | --- |
+| a\|b | 5 |'''
+            "#,
+        )
+        .unwrap();
+        assert_eq!(extract_value, expect_value);
+    }
+
+    #[test]
+    fn test_wildcard_attr() {
+        test_case! {
+            html: r#"<section><div id="x" class="c" data-id="1" data-name="n"></div></section>"#,
+            opt: r#"
+                selector = "section"
+
+                [all]
+                target = "@*"
+                selector = "div"
+
+                [data]
+                target = "data-*"
+                selector = "div"
+            "#,
+            expect: r#"
+                [all]
+                id.text = "x"
+                class.text = "c"
+                data-id.text = "1"
+                data-name.text = "n"
+                [data]
+                data-id.text = "1"
+                data-name.text = "n"
+            "#
+        };
+    }
+
+    #[test]
+    fn test_meta() {
+        test_case! {
+            html: r#"<div><p>a</p><ul><li>b</li><li>c</li></ul></div>"#,
+            opt: r#"
+                selector = "div"
+
+                [li]
+                target = ["tag", "css_path", "index", "sibling_index", "depth", "ancestors"]
+                selector = "li"
+            "#,
+            expect: r#"
+                [[li]]
+                text = ["li", "html > div:nth-child(1) > ul:nth-child(2) > li:nth-child(1)", "0", "0", "3", "html > div > ul"]
+                [[li]]
+                text = ["li", "html > div:nth-child(1) > ul:nth-child(2) > li:nth-child(2)", "1", "1", "3", "html > div > ul"]
+            "#
+        };
+    }
+
+    #[test]
+    fn test_structured_data() {
+        test_case! {
+            html: r#"
+<main><section>
+    <meta property="og:title" content="Shoe">
+    <meta property="og:image" content="/a.png">
+    <meta property="og:image" content="/b.png">
+    <meta name="twitter:card" content="summary">
+    <script type="application/ld+json">
+        {"@context": "https://schema.org", "@graph": [{"@type": "Product", "name": "Shoe", "sku": null}]}
+    </script>
+<div itemscope itemtype="https://schema.org/Product">
+    <span itemprop="name">Shoe</span>
+    <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+        <meta itemprop="price" content="5">
+        <a itemprop="url" href="/shoe">buy</a>
+    </div>
+</div>
+<div vocab="https://schema.org/" typeof="Product" resource="urn:shoe">
+    <span property="name">Shoe</span>
+    <div property="offers" typeof="Offer">
+        <span property="price" content="5">$5</span>
+        <time property="validFrom" datetime="2024-01-01">Jan 1</time>
+    </div>
+</div>
+</section></main>
+            "#,
+            opt: r#"
+                selector = "main"
+
+                [ld]
+                kind = "json_ld"
+                selector = "section"
+
+                [micro]
+                kind = "microdata"
+                selector = "section"
+
+                [rdfa]
+                kind = "rdfa"
+                selector = "section"
+
+                [og]
+                kind = "opengraph"
+                selector = "section"
+
+                [twitter]
+                kind = "twitter_card"
+                selector = "section"
+            "#,
+            expect: r#"
+                [ld]
+                "@type".text = "Product"
+                name.text = "Shoe"
+                [micro]
+                "@type".text = "https://schema.org/Product"
+                name.text = "Shoe"
+                [micro.offers]
+                "@type".text = "https://schema.org/Offer"
+                price.text = "5"
+                url.text = "/shoe"
+                [rdfa]
+                "@type".text = "https://schema.org/Product"
+                "@id".text = "urn:shoe"
+                name.text = "Shoe"
+                [rdfa.offers]
+                "@type".text = "https://schema.org/Offer"
+                price.text = "5"
+                validFrom.text = "2024-01-01"
+                [og]
+                title.text = "Shoe"
+                image.text = ["/a.png", "/b.png"]
+                [twitter]
+                card.text = "summary"
+            "#
+        };
+
+        for key in [
+            r#"type = "int""#,
+            r#"json_path = "$""#,
+            r#"transform = ["trim"]"#,
+        ] {
+            let opt: ExtractOpt =
+                toml::from_str(&format!("kind = \"json_ld\"\nselector = \"main\"\n{}", key))
+                    .unwrap();
+            assert!(opt.compile().is_err());
+        }
+    }
+
+    #[test]
+    fn test_json_path() {
+        test_case! {
+            html: r#"
+<div>
+    <script>window.__INITIAL_STATE__ = {"product": {"offers": [{"price": "1.50"}, {"price": 2}]}};</script>
+    <span data-props='{"tags": ["a", "b"], "id": 7, "gone": null, "meta": {"a": 1, "b": null}}'></span>
+</div>
+            "#,
+            opt: r#"
+                selector = "div"
+
+                [price]
+                target = "text"
+                selector = "script"
+                regex = '__INITIAL_STATE__ = (.*);'
+                json_path = "$.product.offers[*].price"
+                type = "float"
+
+                [tags]
+                target = "data-props"
+                selector = "span"
+                json_path = "$..tags[-1:]"
+
+                [id]
+                target = "data-props"
+                selector = "span"
+                json_path = "$['id']"
+
+                [gone]
+                target = "data-props"
+                selector = "span"
+                json_path = "$.gone"
+
+                [meta]
+                target = "data-props"
+                selector = "span"
+                json_path = "$.meta"
+            "#,
+            expect: r#"
+                price.text = [1.5, 2.0]
+                tags.text = "b"
+                id.text = 7
+                gone = {}
+                meta.text = { a = 1 }
+            "#
+        };
+    }
+
+    #[test]
+    fn test_parse_as() {
+        test_case! {
+            html: r#"
+<div>
+    <span data-content="&lt;b&gt;Bold&lt;/b&gt; &lt;a href='/x'&gt;link&lt;/a&gt;"></span>
+</div>
+            "#,
+            opt: r#"
+                selector = "div"
+
+                [tooltip]
+                target = "data-content"
+                selector = "span"
+                parse_as = "html"
+
+                [tooltip.bold]
+                target = "text"
+                selector = "b"
+
+                [tooltip.link]
+                target = "href"
+                selector = "a"
+            "#,
+            expect: r#"
+                [tooltip]
+                text = "<b>Bold</b> <a href='/x'>link</a>"
+                bold.text = "Bold"
+                link.text = "/x"
+            "#
+        };
+    }
+
+    #[test]
+    fn test_form() {
+        let opt: ExtractOpt = toml::from_str(
+            r#"
+                selector = "div"
+
+                [search]
+                kind = "form"
+                selector = "form"
+            "#,
+        )
+        .unwrap();
+        let ctx = ExtractContext::new().with_base_url(Url::parse("https://x.com/a/").unwrap());
+        let extract = extract_fragment_with(
+            r#"
+<div><form action="search" method="POST">
+    <input name="q" value="shoe">
+    <input type="checkbox" name="new" checked>
+    <input type="checkbox" name="used" value="1">
+    <select name="sort"><option value="price">Price</option><option selected>Date</option></select>
+    <button>Go</button>
+</form></div>
+            "#,
+            &opt.compile().unwrap(),
+            &ctx,
+        );
+        let extract_value = toml::Value::try_from(extract).unwrap();
+        let expect_value = toml::from_str(
+            r#"
+                [search]
+                action.text = "https://x.com/a/search"
+                method.text = "post"
+                enctype.text = "application/x-www-form-urlencoded"
+                payload = { q.text = "shoe", new.text = "on", sort.text = "Date" }
+
+                [[search.controls]]
+                name.text = "q"
+                type.text = "text"
+                value.text = "shoe"
+                [[search.controls]]
+                name.text = "new"
+                type.text = "checkbox"
+                value.text = "on"
+                checked.text = true
+                [[search.controls]]
+                name.text = "used"
+                type.text = "checkbox"
+                value.text = "1"
+                checked.text = false
+                [[search.controls]]
+                name.text = "sort"
+                type.text = "select-one"
+                value.text = "Date"
+                options = [
+                    { value.text = "price", text.text = "Price", selected.text = false },
+                    { value.text = "Date", text.text = "Date", selected.text = true },
+                ]
+                [[search.controls]]
+                type.text = "submit"
+                value.text = ""
+            "#,
+        )
+        .unwrap();
+        assert_eq!(extract_value, expect_value);
+    }
+
+    #[test]
+    fn test_links() {
+        let opt: ExtractOpt = toml::from_str(
+            r#"
+                selector = "div"
+
+                [links]
+                kind = "links"
+                selector = "nav"
+                links_dedup = true
+                links_canonicalize = true
+            "#,
+        )
+        .unwrap();
+        let ctx = ExtractContext::new().with_base_url(Url::parse("https://x.com/a/").unwrap());
+        let extract = extract_fragment_with(
+            r#"
+<div><nav>
+    <a href="p?b=2&a=1#top" rel="Next">Next  page</a>
+    <a href="p?a=1&b=2">Again</a>
+    <link rel="alternate" hreflang="fr" href="https://y.com/fr">
+</nav></div>
+            "#,
+            &opt.compile().unwrap(),
+            &ctx,
+        );
+        let extract_value = toml::Value::try_from(extract).unwrap();
+        let expect_value = toml::from_str(
+            r#"
+                [[links]]
+                url.text = "https://x.com/a/p?a=1&b=2"
+                tag.text = "a"
+                text.text = "Next page"
+                rel.text = ["next"]
+                internal.text = true
+                [[links]]
+                url.text = "https://y.com/fr"
+                tag.text = "link"
+                rel.text = ["alternate"]
+                hreflang.text = "fr"
+                internal.text = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(extract_value, expect_value);
+    }
+
+    #[test]
+    fn test_order() {
+        let opt: ExtractOpt = toml::from_str(
+            r#"
+                selector = "div"
+
+                [zeta]
+                target = "text"
+                selector = "b"
+
+                [alpha]
+                target = "text"
+                selector = "i"
+
+                [mu]
+                regex = '(?P<y>\d+)-(?P<x>\d+)'
+                target = "text"
+                selector = "span"
+            "#,
+        )
+        .unwrap();
+        let extract = extract_fragment(
+            "<div><i>1</i><b>2</b><span>3-4</span></div>",
+            &opt.compile().unwrap(),
+        );
+        assert_eq!(
+            serde_json::to_string(&extract).unwrap(),
+            r#"{"zeta":{"text":"2"},"alpha":{"text":"1"},"mu":{"y":{"text":"3"},"x":{"text":"4"}}}"#
+        );
+    }
+
+    #[test]
+    fn test_explicit_items() {
+        test_case! {
+            html: r#"<div><b>a</b><i>b</i></div>"#,
+            opt: r#"
+                selector = "div"
+
+                [items.selector]
+                target = "text"
+                selector = "b"
+
+                [items.regex]
+                target = "text"
+                selector = "i"
+            "#,
+            expect: r#"
+                selector.text = "a"
+                regex.text = "b"
+            "#
+        };
+
+        let mixed = toml::from_str::<ExtractOpt>(
+            r#"
+                selector = "div"
+                selectr = "b"
+
+                [fields.name]
+                selector = "b"
+            "#,
+        );
+        assert!(mixed
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("unknown keyword `selectr`"));
+        let typo = toml::from_str::<ExtractOpt>(r#"selectr = "div""#);
+        assert!(typo
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("unknown keyword `selectr`"));
+    }
+
+    #[test]
+    fn test_extract_into() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Product {
+            name: String,
+            price: f64,
+            tags: Vec<String>,
+            note: Option<String>,
+            stock: Stock,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Stock {
+            text: String,
+            count: u32,
+        }
+
+        let opt: ExtractOpt = toml::from_str(
+            r#"
+                selector = ".product"
+
+                [name]
+                target = "text"
+                selector = "h1"
+
+                [price]
+                target = "text"
+                selector = ".price"
+
+                [tags]
+                target = "text"
+                selector = "li"
+
+                [note]
+                target = "text"
+                selector = ".note"
+
+                [stock]
+                target = "data-state"
+                selector = ".stock"
+
+                [stock.count]
+                target = "text"
+                selector = "b"
+            "#,
+        )
+        .unwrap();
+        let opt = opt.compile().unwrap();
+        let html = r#"
+<div class="product">
+    <h1>Shoe</h1><span class="price">9.5</span>
+    <ul><li>new</li></ul>
+    <p class="stock" data-state="in"><b>3</b></p>
+</div>
+        "#;
+        let product: Product = extract_fragment_into(html, &opt).unwrap();
+        assert_eq!(
+            product,
+            Product {
+                name: "Shoe".to_owned(),
+                price: 9.5,
+                tags: vec!["new".to_owned()],
+                note: None,
+                stock: Stock {
+                    text: "in".to_owned(),
+                    count: 3,
+                },
+            }
+        );
+
+        let err = extract_fragment_into::<Product>(&html.replace("<b>3</b>", "<b>many</b>"), &opt)
+            .unwrap_err();
+        assert_eq!(err.path, "stock.count");
+        assert!(matches!(err.kind, ExtractErrorKind::Deserialize { .. }));
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Partial {
+            stock: Unit,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Unit {
+            count: u32,
+            unit: String,
+        }
+        let err = extract_fragment_into::<Partial>(html, &opt).unwrap_err();
+        assert_eq!(err.path, "stock.unit");
+
+        let err = extract_fragment_into::<Product>(&html.repeat(2), &opt).unwrap_err();
+        assert_eq!(err.path, "");
+        assert_eq!(
+            err.to_string(),
+            "<root>: can not deserialize: expected one item, found 2"
+        );
+    }
+
+    #[test]
+    fn test_type() {
+        test_case! {
+            html: r#"
+<div class="parent"><span>42</span><i>3.5</i><b>yes</b><em>12/03/2022</em></div>
+            "#,
+            opt: r#"
+                selector = ".parent"
+
+                [int]
+                target = "text"
+                selector = "span"
+                type = "int"
+
+                [float]
+                target = "text"
+                selector = "i"
+                type = "float"
+
+                [bool]
+                target = "text"
+                selector = "b"
+                type = "bool"
+
+                [date]
+                target = "text"
+                selector = "em"
+                type = "datetime"
+                format = "%d/%m/%Y"
+            "#,
+            expect: r#"
+                [int]
+                text = 42
+                [float]
+                text = 3.5
+                [bool]
+                text = true
+                [date]
+                text = "2022-03-12"
+            "#
+        };
+    }
+
+    #[test]
+    fn test_transform() {
+        test_case! {
+            html: r#"
+<div class="parent"> Hello,   <b>World</b>! &amp;lt;tag&amp;gt; </div>
+            "#,
+            opt: r#"
+                target = "text"
+                selector = ".parent"
+                transform = [
+                    "collapse_whitespace",
+                    "lowercase",
+                    { replace = { pattern = "!", with = "" } },
+                    "html_unescape",
+                    { split = { sep = " " } },
+                ]
+            "#,
+            expect: r#"
+                text = ["hello,", "world", "<tag>"]
+            "#
+        };
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        let opt: ExtractOpt = toml::from_str(
+            r#"
+                selector = "div"
+
+                [link]
+                target = "href"
+                selector = "a"
+                resolve_url = true
+
+                [image]
+                target = "srcset"
+                selector = "img"
+                resolve_url = true
+            "#,
+        )
+        .unwrap();
+        let ctx = ExtractContext::new().with_base_url(Url::parse("https://x.com/a/b/1").unwrap());
+        let extract = extract_fragment_with(
+            r#"<div><a href="../p/2"></a><img srcset="s.png 1x, /l.png 2x"></div>"#,
+            &opt.compile().unwrap(),
+            &ctx,
+        );
+        let extract_value = toml::Value::try_from(extract).unwrap();
+        let expect_value = toml::from_str(
+            r#"
+                [link]
+                text = "https://x.com/a/p/2"
+                [image]
+                text = "https://x.com/a/b/s.png 1x, https://x.com/l.png 2x"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(extract_value, expect_value);
+    }
+
+    #[test]
+    fn test_xpath() {
+        test_case! {
+            html: r#"
+<ul>
+    <li><span>Price</span><b>5</b></li>
+    <li><span>Stock</span><b>3</b></li>
+</ul>
+            "#,
+            opt: r#"
+                selector = "ul"
+
+                [stock]
+                target = "text"
+                xpath = ".//span[contains(text(), 'Stock')]/following-sibling::b"
+
+                [first]
+                target = "text"
+                xpath = "li[1]/b"
+
+                [list]
+                xpath = "//b[. = '3']/ancestor::ul"
+
+                [list.count]
+                target = "text"
+                selector = "li:last-child > b"
+            "#,
+            expect: r#"
+                [stock]
+                text = "3"
+                [first]
+                text = "5"
+                [list.count]
+                text = "3"
+            "#
+        };
+    }
+
+    #[test]
+    fn test_required() {
+        let opt: ExtractOpt = toml::from_str(
+            r#"
+                selector = ".product"
+
+                [name]
+                target = "text"
+                selector = "h2"
+                default = "unknown"
+
+                [price]
+                target = "text"
+                selector = ".price"
+                required = true
+            "#,
+        )
+        .unwrap();
+        let opt = opt.compile().unwrap();
+        let html = "<div class=\"product\"><span>$5</span></div>";
+
+        let err = try_extract_fragment(html, &opt).unwrap_err();
+        assert_eq!(err.path, "price");
+        assert!(matches!(
+            err.kind,
+            ExtractErrorKind::Missing { ref selector } if selector == ".price"
+        ));
+        // the required one is missing if its parent matched nothing
+        let err = try_extract_fragment("<p>none</p>", &opt).unwrap_err();
+        assert_eq!(err.path, "price");
+
+        let extract_value = toml::Value::try_from(extract_fragment(html, &opt)).unwrap();
+        let expect_value = toml::from_str(
+            r#"
+                price = []
+                [name]
+                text = "unknown"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(extract_value, expect_value);
+    }
+
+    #[test]
+    fn test_type_error() {
+        let opt: ExtractOpt = toml::from_str(
+            r#"
+                selector = ".parent"
+
+                [price]
+                target = "text"
+                selector = "span"
+                type = "int"
+            "#,
+        )
+        .unwrap();
+        let err = try_extract_fragment(
+            "<div class=\"parent\"><span>free</span></div>",
+            &opt.compile().unwrap(),
+        )
+        .unwrap_err();
+        assert_eq!(err.path, "price");
+        assert!(matches!(err.kind, ExtractErrorKind::Convert { .. }));
+    }
 }
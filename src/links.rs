@@ -0,0 +1,88 @@
+use crate::structured::json_item;
+use crate::{ExtractItem, State};
+use scraper::{ElementRef, Selector};
+use serde_json::{json, Map, Value as Json};
+use std::collections::HashSet;
+use url::Url;
+
+/// The option of `kind = "links"`
+#[derive(Default, Clone, Debug)]
+pub struct LinksOpt {
+    /// Drop the links whose url appeared before
+    pub dedup: bool,
+    /// Drop the fragment and sort the query of urls
+    pub canonicalize: bool,
+}
+
+/// Drop the fragment and the empty query, and sort the query pairs.
+fn canonicalize(url: &mut Url) {
+    url.set_fragment(None);
+    let mut pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        pairs.sort();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+}
+
+/// The links of `<a>`, `<link>` and `<area>` with the resolved `url`, `text`, `rel`, `hreflang`,
+/// and whether it's `internal` to the host of base url.
+pub(crate) fn extract_links(elem: ElementRef, links: &LinksOpt, state: &State) -> Vec<ExtractItem> {
+    let selector = Selector::parse("a[href], link[href], area[href]").unwrap();
+    let base_url = state.base_url.as_ref();
+    let mut seen = HashSet::new();
+    let mut items = vec![];
+    for link in elem.select(&selector) {
+        let value = link.value();
+        let href = value.attr("href").unwrap().trim();
+        let url = match base_url {
+            Some(base_url) => base_url.join(href).ok(),
+            None => Url::parse(href).ok(),
+        };
+        let (url, internal) = match url {
+            Some(mut url) => {
+                if links.canonicalize {
+                    canonicalize(&mut url);
+                }
+                let internal = base_url.map(|x| x.origin() == url.origin());
+                (url.to_string(), internal)
+            }
+            // the relative url without base url
+            None => (href.to_owned(), Some(true)),
+        };
+        if links.dedup && !seen.insert(url.clone()) {
+            continue;
+        }
+
+        let text = match value.name() {
+            "area" => value.attr("alt").unwrap_or_default().to_owned(),
+            _ => link
+                .text()
+                .flat_map(str::split_whitespace)
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        let mut map = Map::new();
+        map.insert("url".to_owned(), json!(url));
+        map.insert("tag".to_owned(), json!(value.name()));
+        if !text.is_empty() {
+            map.insert("text".to_owned(), json!(text));
+        }
+        if let Some(rel) = value.attr("rel") {
+            let rel: Vec<_> = rel
+                .split_whitespace()
+                .map(|x| json!(x.to_ascii_lowercase()))
+                .collect();
+            map.insert("rel".to_owned(), Json::Array(rel));
+        }
+        if let Some(hreflang) = value.attr("hreflang") {
+            map.insert("hreflang".to_owned(), json!(hreflang.trim()));
+        }
+        if let Some(internal) = internal {
+            map.insert("internal".to_owned(), json!(internal));
+        }
+        items.push(json_item(Json::Object(map)));
+    }
+    items
+}
@@ -0,0 +1,261 @@
+use crate::resolve;
+use crate::table;
+use crate::text::{BLOCKS, SKIPPED};
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Node};
+use url::Url;
+
+/// Escape the characters of inline markdown syntax.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Wrap the inline content by the delimiters, keeping the whitespace around outside.
+fn wrap(inner: &str, open: &str, close: &str) -> String {
+    let text = inner.trim();
+    if text.is_empty() {
+        return inner.to_owned();
+    }
+    let lead = if inner.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let trail = if inner.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", lead, open, text, close, trail)
+}
+
+/// Prefix the first line by `first` and the following ones by `rest`.
+fn indent(content: &str, first: &str, rest: &str) -> String {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match (i, line.is_empty()) {
+            (0, _) => format!("{}{}", first, line).trim_end().to_owned(),
+            (_, true) => rest.trim_end().to_owned(),
+            (_, false) => format!("{}{}", rest, line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The longest run of the char in text.
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|x| x != c).map(str::len).max().unwrap_or(0)
+}
+
+/// Push the pending inline content as a paragraph, collapsing the whitespace in lines.
+fn paragraph(blocks: &mut Vec<String>, inline: &mut String) {
+    let text = inline
+        .lines()
+        .map(|x| x.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = text.trim_end_matches('\\').trim_end();
+    if !text.is_empty() {
+        blocks.push(text.to_owned());
+    }
+    inline.clear();
+}
+
+fn is_element(node: &NodeRef<Node>, name: &str) -> bool {
+    node.value().as_element().is_some_and(|x| x.name() == name)
+}
+
+/// Convert the html into CommonMark, with the tables of GFM.
+struct Markdown<'a> {
+    base_url: Option<&'a Url>,
+}
+
+impl Markdown<'_> {
+    fn url(&self, url: &str) -> String {
+        let url = match self.base_url {
+            Some(base_url) => resolve::resolve(base_url, url),
+            None => url.trim().to_owned(),
+        };
+        if url.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+            format!("<{}>", url)
+        } else {
+            url
+        }
+    }
+
+    /// Render the nodes as blocks, the adjacent inline ones are grouped into paragraphs.
+    fn blocks<'a>(&self, nodes: impl Iterator<Item = NodeRef<'a, Node>>, sep: &str) -> String {
+        let mut blocks = vec![];
+        let mut inline = String::new();
+        for node in nodes {
+            match self.block(node) {
+                Some(block) => {
+                    paragraph(&mut blocks, &mut inline);
+                    if !block.is_empty() {
+                        blocks.push(block);
+                    }
+                }
+                None => inline.push_str(&self.inline(node)),
+            }
+        }
+        paragraph(&mut blocks, &mut inline);
+        blocks.join(sep)
+    }
+
+    /// Render the block element, `None` if it's not a block.
+    fn block(&self, node: NodeRef<Node>) -> Option<String> {
+        let elem = node.value().as_element()?;
+        let name = elem.name();
+        if SKIPPED.contains(&name) || elem.attr("hidden").is_some() {
+            return Some(String::new());
+        }
+        Some(match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let mut blocks = vec![];
+                paragraph(&mut blocks, &mut self.inlines(node).replace('\n', " "));
+                match blocks.pop() {
+                    Some(text) => format!("{} {}", "#".repeat(name[1..].parse().unwrap()), text),
+                    None => String::new(),
+                }
+            }
+            "ul" | "ol" => {
+                let start = elem
+                    .attr("start")
+                    .and_then(|x| x.trim().parse().ok())
+                    .unwrap_or(1);
+                self.list(node, (name == "ol").then_some(start))
+            }
+            "pre" => code_block(node),
+            "blockquote" => indent(&self.blocks(node.children(), "\n\n"), "> ", "> "),
+            "hr" => "---".to_owned(),
+            "table" => self.table(ElementRef::wrap(node).unwrap()),
+            name if BLOCKS.contains(&name) => self.blocks(node.children(), "\n\n"),
+            _ => return None,
+        })
+    }
+
+    /// Render the items of list, numbered from `start` if ordered.
+    fn list(&self, node: NodeRef<Node>, start: Option<usize>) -> String {
+        node.children()
+            .filter(|x| is_element(x, "li"))
+            .enumerate()
+            .map(|(i, item)| {
+                let marker = match start {
+                    Some(start) => format!("{}. ", start + i),
+                    None => "- ".to_owned(),
+                };
+                let content = self.blocks(item.children(), "\n");
+                indent(&content, &marker, &" ".repeat(marker.len()))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn table(&self, elem: ElementRef) -> String {
+        let mut grid = table::layout(elem);
+        let width = grid.iter().map(|x| x.0.len()).max().unwrap_or(0);
+        if width == 0 {
+            return String::new();
+        }
+        let header = match grid.first() {
+            Some((_, true)) => grid.remove(0).0,
+            _ => vec![],
+        };
+        let row = |cells: &[String]| {
+            let cells: Vec<_> = (0..width)
+                .map(|i| match cells.get(i) {
+                    Some(cell) => escape(&cell.split_whitespace().collect::<Vec<_>>().join(" "))
+                        .replace('|', "\\|"),
+                    None => String::new(),
+                })
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let mut lines = vec![row(&header), format!("|{}", " --- |".repeat(width))];
+        lines.extend(grid.iter().map(|(cells, _)| row(cells)));
+        lines.join("\n")
+    }
+
+    fn inlines(&self, node: NodeRef<Node>) -> String {
+        node.children().map(|x| self.inline(x)).collect()
+    }
+
+    /// Render the node as inline content, the block elements inside are flattened.
+    fn inline(&self, node: NodeRef<Node>) -> String {
+        let elem = match node.value() {
+            Node::Text(text) => return escape(&text.replace(char::is_whitespace, " ")),
+            Node::Element(elem) => elem,
+            _ => return String::new(),
+        };
+        let name = elem.name();
+        if SKIPPED.contains(&name) || elem.attr("hidden").is_some() {
+            return String::new();
+        }
+        match name {
+            "br" => "\\\n".to_owned(),
+            "img" => match elem.attr("src") {
+                Some(src) => format!(
+                    "![{}]({})",
+                    escape(elem.attr("alt").unwrap_or_default()),
+                    self.url(src)
+                ),
+                None => String::new(),
+            },
+            "code" | "kbd" | "samp" => {
+                let text = ElementRef::wrap(node).unwrap().text().collect::<String>();
+                let text = text.replace(char::is_whitespace, " ");
+                let fence = "`".repeat(longest_run(&text, '`') + 1);
+                match text.trim() {
+                    "" => text,
+                    code if fence.len() > 1 => format!("{} {} {}", fence, code, fence),
+                    code => format!("{}{}{}", fence, code, fence),
+                }
+            }
+            "strong" | "b" => wrap(&self.inlines(node), "**", "**"),
+            "em" | "i" => wrap(&self.inlines(node), "*", "*"),
+            "del" | "s" | "strike" => wrap(&self.inlines(node), "~~", "~~"),
+            "a" => {
+                let inner = self.inlines(node);
+                match elem.attr("href") {
+                    Some(href) if inner.trim().is_empty() => format!("<{}>", self.url(href)),
+                    Some(href) => wrap(&inner, "[", &format!("]({})", self.url(href))),
+                    None => inner,
+                }
+            }
+            _ => self.inlines(node),
+        }
+    }
+}
+
+/// Render `<pre>` as a fenced code block, with the language of `<code class="language-*">`.
+fn code_block(node: NodeRef<Node>) -> String {
+    let lang = node
+        .children()
+        .filter_map(ElementRef::wrap)
+        .find(|x| x.value().name() == "code")
+        .and_then(|x| {
+            x.value().classes().find_map(|x| {
+                x.strip_prefix("language-")
+                    .or_else(|| x.strip_prefix("lang-"))
+            })
+        })
+        .unwrap_or_default();
+    let text = ElementRef::wrap(node).unwrap().text().collect::<String>();
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    let fence = "`".repeat(longest_run(text, '`').max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, lang, text, fence)
+}
+
+/// The markdown of the element, with the urls of links and images resolved against the base url.
+pub(crate) fn markdown(elem: ElementRef, base_url: Option<&Url>) -> String {
+    Markdown { base_url }.blocks(std::iter::once(*elem), "\n\n")
+}
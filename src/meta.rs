@@ -0,0 +1,43 @@
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Node};
+
+/// The element ancestors from the root down to the parent.
+fn ancestor_elements(elem: ElementRef) -> Vec<ElementRef> {
+    let mut ancestors: Vec<_> = elem.ancestors().filter_map(ElementRef::wrap).collect();
+    ancestors.reverse();
+    ancestors
+}
+
+/// The position from 0 among the sibling elements.
+pub(crate) fn sibling_index(elem: ElementRef) -> usize {
+    elem.prev_siblings()
+        .filter(|x: &NodeRef<Node>| x.value().is_element())
+        .count()
+}
+
+/// The count of element ancestors, 0 for the root.
+pub(crate) fn depth(elem: ElementRef) -> usize {
+    elem.ancestors().filter(|x| x.value().is_element()).count()
+}
+
+/// The tag names of ancestors, e.g. `html > body > div`.
+pub(crate) fn ancestors(elem: ElementRef) -> String {
+    ancestor_elements(elem)
+        .iter()
+        .map(|x| x.value().name())
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
+
+/// The selector selecting exactly the element from the root, e.g. `html > body:nth-child(2) > p:nth-child(1)`.
+pub(crate) fn css_path(elem: ElementRef) -> String {
+    let mut path = ancestor_elements(elem);
+    path.push(elem);
+    path.iter()
+        .map(|x| match x.parent().and_then(ElementRef::wrap) {
+            Some(_) => format!("{}:nth-child({})", x.value().name(), sibling_index(*x) + 1),
+            None => x.value().name().to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum OneOrList<T> {
     One(T),
@@ -15,6 +15,13 @@ impl<T> OneOrList<T> {
         }
     }
 
+    pub fn into_vec(self) -> Vec<T> {
+        match self {
+            Self::One(x) => vec![x],
+            Self::List(x) => x,
+        }
+    }
+
     #[allow(dead_code)]
     pub fn as_slice_mut(&mut self) -> &mut [T] {
         match self {
@@ -0,0 +1,128 @@
+use anyhow::{bail, Result};
+use regex::{Captures, Regex, RegexBuilder};
+use serde::Deserialize;
+
+/// How the regex is applied on the text
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RegexMode {
+    /// Extract the captures of the first match
+    #[default]
+    First,
+    /// Extract the captures of all matches
+    All,
+    /// Replace all matches with `regex_replace`
+    Replace,
+    /// Keep the text as it is if matched, and drop the element if none of its text matched
+    Filter,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegexFlag {
+    CaseInsensitive,
+    Multiline,
+    DotAll,
+}
+
+pub enum PatternMode {
+    First,
+    All,
+    Replace(String),
+    Filter,
+}
+
+/// The compiled regex with its mode
+pub struct Pattern {
+    pub regex: Regex,
+    pub mode: PatternMode,
+}
+
+impl Pattern {
+    pub(crate) fn compile(
+        regex: &str,
+        mode: RegexMode,
+        flags: &[RegexFlag],
+        replace: Option<String>,
+    ) -> Result<Self> {
+        let mut builder = RegexBuilder::new(regex);
+        for flag in flags {
+            match flag {
+                RegexFlag::CaseInsensitive => builder.case_insensitive(true),
+                RegexFlag::Multiline => builder.multi_line(true),
+                RegexFlag::DotAll => builder.dot_matches_new_line(true),
+            };
+        }
+        let mode = match (mode, replace) {
+            (RegexMode::Replace, Some(replace)) => PatternMode::Replace(replace),
+            (RegexMode::Replace, None) => bail!("`regex_replace` is required by mode `replace`"),
+            (_, Some(_)) => bail!("`regex_replace` is only available for mode `replace`"),
+            (RegexMode::First, None) => PatternMode::First,
+            (RegexMode::All, None) => PatternMode::All,
+            (RegexMode::Filter, None) => PatternMode::Filter,
+        };
+        Ok(Pattern {
+            regex: builder.build()?,
+            mode,
+        })
+    }
+
+    /// Apply on the text, the named captures are pushed into `named`, the others into `texts`.
+    /// Return whether the text matched.
+    pub(crate) fn apply<'a>(
+        &'a self,
+        text: String,
+        texts: &mut Vec<String>,
+        named: &mut Vec<(&'a str, String)>,
+    ) -> bool {
+        match &self.mode {
+            PatternMode::First => match self.regex.captures(&text) {
+                Some(captures) => {
+                    self.push_captures(captures, texts, named);
+                    true
+                }
+                None => false,
+            },
+            PatternMode::All => {
+                let mut matched = false;
+                for captures in self.regex.captures_iter(&text) {
+                    self.push_captures(captures, texts, named);
+                    matched = true;
+                }
+                matched
+            }
+            PatternMode::Replace(replace) => {
+                let matched = self.regex.is_match(&text);
+                texts.push(self.regex.replace_all(&text, replace.as_str()).into_owned());
+                matched
+            }
+            PatternMode::Filter => {
+                let matched = self.regex.is_match(&text);
+                if matched {
+                    texts.push(text);
+                }
+                matched
+            }
+        }
+    }
+
+    /// Push the captures, or the whole match if there is no capture group.
+    fn push_captures<'a>(
+        &'a self,
+        captures: Captures,
+        texts: &mut Vec<String>,
+        named: &mut Vec<(&'a str, String)>,
+    ) {
+        if captures.len() == 1 {
+            texts.push(captures[0].to_owned());
+            return;
+        }
+        for (capture, name) in captures.iter().zip(self.regex.capture_names()).skip(1) {
+            match (capture, name) {
+                (Some(capture), Some(name)) => named.push((name, capture.as_str().to_owned())),
+                (Some(capture), None) => texts.push(capture.as_str().to_owned()),
+                (None, _) => {}
+            }
+        }
+    }
+}
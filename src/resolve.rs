@@ -0,0 +1,69 @@
+use scraper::{Html, Selector};
+use url::Url;
+
+/// The attributes whose value is url
+const URL_ATTRS: &[&str] = &[
+    "href",
+    "src",
+    "srcset",
+    "action",
+    "formaction",
+    "cite",
+    "data",
+    "poster",
+    "background",
+    "longdesc",
+    "manifest",
+    "icon",
+];
+
+/// The base url of html, which is the `<base href>` resolved against the given one if exists.
+pub(crate) fn base_url(html: &Html, base_url: Option<&Url>) -> Option<Url> {
+    let selector = Selector::parse("base[href]").unwrap();
+    let href = html
+        .select(&selector)
+        .next()
+        .and_then(|x| x.value().attr("href"));
+    match (href, base_url) {
+        (Some(href), Some(base_url)) => base_url
+            .join(href.trim())
+            .ok()
+            .or_else(|| Some(base_url.clone())),
+        (Some(href), None) => Url::parse(href.trim()).ok(),
+        (None, base_url) => base_url.cloned(),
+    }
+}
+
+/// Resolve the value of attribute if it's url, otherwise return as it is.
+pub(crate) fn resolve_attr(base_url: &Url, attr: &str, value: &str) -> String {
+    match attr {
+        "srcset" => resolve_srcset(base_url, value),
+        attr if URL_ATTRS.contains(&attr) => resolve(base_url, value),
+        _ => value.to_owned(),
+    }
+}
+
+/// Resolve the url, and return as it is if failed.
+pub(crate) fn resolve(base_url: &Url, url: &str) -> String {
+    base_url
+        .join(url.trim())
+        .map(String::from)
+        .unwrap_or_else(|_| url.to_owned())
+}
+
+/// Resolve every candidate url in srcset, keeping the descriptors.
+fn resolve_srcset(base_url: &Url, srcset: &str) -> String {
+    srcset
+        .split(',')
+        .map(|candidate| {
+            let candidate = candidate.trim();
+            match candidate.split_once(char::is_whitespace) {
+                Some((url, descriptor)) => {
+                    format!("{} {}", resolve(base_url, url), descriptor.trim())
+                }
+                None => resolve(base_url, candidate),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,257 @@
+use crate::{
+    Extract, ExtractErrorKind, ExtractItem, ExtractOptCompiled, ExtractText, State, Value,
+    ValueType,
+};
+use indexmap::IndexMap;
+use scraper::{ElementRef, Selector};
+use serde_json::Map;
+use std::iter::once;
+
+type Json = serde_json::Value;
+
+/// Convert the json into the item extracted, the members of object into the nested items.
+pub(crate) fn json_item(value: Json) -> ExtractItem {
+    match value {
+        Json::Object(map) => ExtractItem {
+            text: None,
+            items: map
+                .into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, json_extract(v)))
+                .collect(),
+        },
+        value => ExtractItem {
+            text: Some(ExtractText::One(Value::from(value))),
+            items: IndexMap::new(),
+        },
+    }
+}
+
+/// Convert the json into the result extracted, the array of scalars is the text list of one item.
+pub(crate) fn json_extract(value: Json) -> Extract {
+    match value {
+        Json::Array(values)
+            if !values.is_empty() && values.iter().all(|x| !x.is_object() && !x.is_array()) =>
+        {
+            Extract::One(ExtractItem {
+                text: Some(ExtractText::List(
+                    values
+                        .into_iter()
+                        .filter(|x| !x.is_null())
+                        .map(Value::from)
+                        .collect(),
+                )),
+                items: IndexMap::new(),
+            })
+        }
+        Json::Array(values) => Extract::List(values.into_iter().map(json_item).collect()),
+        value => Extract::One(json_item(value)),
+    }
+}
+
+/// Insert the value into object, the repeated ones are collected into an array.
+pub(crate) fn insert(map: &mut Map<String, Json>, key: String, value: Json) {
+    match map.get_mut(&key) {
+        Some(Json::Array(values)) => values.push(value),
+        Some(prev) => *prev = Json::Array(vec![prev.take(), value]),
+        None => {
+            map.insert(key, value);
+        }
+    }
+}
+
+/// The documents of `<script type="application/ld+json">`, the ones in `@graph` are flattened.
+pub(crate) fn json_ld(elem: ElementRef, state: &mut State) -> Vec<ExtractItem> {
+    let selector = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    let mut items = vec![];
+    for script in elem.select(&selector) {
+        let text = script.text().collect::<String>();
+        let json = match serde_json::from_str::<Json>(text.trim()) {
+            Ok(json) => json,
+            Err(e) => {
+                state.error(ExtractErrorKind::Convert {
+                    text,
+                    ty: ValueType::Json,
+                    reason: format!("{}", e),
+                });
+                continue;
+            }
+        };
+        let documents = match json {
+            Json::Array(documents) => documents,
+            Json::Object(mut map) => match map.remove("@graph") {
+                Some(Json::Array(graph)) => graph,
+                Some(graph) => vec![graph],
+                None => vec![Json::Object(map)],
+            },
+            _ => vec![],
+        };
+        items.extend(documents.into_iter().map(json_item));
+    }
+    items
+}
+
+/// The elements with the property attribute in the scope, excluding the ones in the nested
+/// scopes, e.g. `itemprop` in `itemscope` or `property` in `typeof`.
+fn properties<'a>(
+    scope: ElementRef<'a>,
+    prop_attr: &str,
+    scope_attr: &str,
+    props: &mut Vec<ElementRef<'a>>,
+) {
+    for child in scope.children().filter_map(ElementRef::wrap) {
+        if child.value().attr(prop_attr).is_some() {
+            props.push(child);
+        }
+        if child.value().attr(scope_attr).is_none() {
+            properties(child, prop_attr, scope_attr, props);
+        }
+    }
+}
+
+/// The value of property, which is the url, the machine-readable attribute or the text.
+fn property_value(elem: ElementRef, opt: &ExtractOptCompiled, state: &State) -> Json {
+    let value = elem.value();
+    let attr = match value.name() {
+        "meta" => Some("content"),
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => Some("src"),
+        "a" | "area" | "link" => Some("href"),
+        "object" => Some("data"),
+        "data" | "meter" => Some("value"),
+        "time" if value.attr("datetime").is_some() => Some("datetime"),
+        _ => None,
+    };
+    Json::String(match attr {
+        Some(attr) => state.attr(opt, attr, value.attr(attr).unwrap_or_default()),
+        None => elem
+            .text()
+            .flat_map(str::split_whitespace)
+            .collect::<Vec<_>>()
+            .join(" "),
+    })
+}
+
+/// The item of `itemscope` with `@type`, `@id` and the properties.
+fn microdata_item(scope: ElementRef, opt: &ExtractOptCompiled, state: &State) -> Json {
+    let mut map = Map::new();
+    if let Some(ty) = scope.value().attr("itemtype") {
+        map.insert("@type".to_owned(), Json::String(ty.trim().to_owned()));
+    }
+    if let Some(id) = scope.value().attr("itemid") {
+        map.insert("@id".to_owned(), Json::String(state.attr(opt, "href", id)));
+    }
+    let mut props = vec![];
+    properties(scope, "itemprop", "itemscope", &mut props);
+    for prop in props {
+        let value = match prop.value().attr("itemscope") {
+            Some(_) => microdata_item(prop, opt, state),
+            None => property_value(prop, opt, state),
+        };
+        for name in prop.value().attr("itemprop").unwrap().split_whitespace() {
+            insert(&mut map, name.to_owned(), value.clone());
+        }
+    }
+    Json::Object(map)
+}
+
+/// The top-level items of microdata, which are `itemscope` not being a property.
+pub(crate) fn microdata(
+    elem: ElementRef,
+    opt: &ExtractOptCompiled,
+    state: &State,
+) -> Vec<ExtractItem> {
+    let selector = Selector::parse("[itemscope]:not([itemprop])").unwrap();
+    elem.select(&selector)
+        .map(|scope| json_item(microdata_item(scope, opt, state)))
+        .collect()
+}
+
+/// The value of RDFa property, which is `content`, the url, `datetime` or the text.
+fn rdfa_value(elem: ElementRef, opt: &ExtractOptCompiled, state: &State) -> Json {
+    let value = elem.value();
+    if let Some(content) = value.attr("content") {
+        return Json::String(content.to_owned());
+    }
+    let url = ["href", "src", "resource"]
+        .into_iter()
+        .find_map(|attr| value.attr(attr));
+    Json::String(match (url, value.attr("datetime")) {
+        (Some(url), _) => state.attr(opt, "href", url),
+        (None, Some(datetime)) => datetime.to_owned(),
+        (None, None) => elem
+            .text()
+            .flat_map(str::split_whitespace)
+            .collect::<Vec<_>>()
+            .join(" "),
+    })
+}
+
+/// The item of RDFa `typeof` with `@type` prefixed by `vocab`, `@id` and the properties.
+fn rdfa_item(scope: ElementRef, opt: &ExtractOptCompiled, state: &State) -> Json {
+    let mut map = Map::new();
+    let vocab = once(scope)
+        .chain(scope.ancestors().filter_map(ElementRef::wrap))
+        .find_map(|x| x.value().attr("vocab"))
+        .map(str::trim);
+    for ty in scope.value().attr("typeof").unwrap().split_whitespace() {
+        let ty = match vocab {
+            Some(vocab) if !ty.contains(':') => format!("{}{}", vocab, ty),
+            _ => ty.to_owned(),
+        };
+        insert(&mut map, "@type".to_owned(), Json::String(ty));
+    }
+    let id = ["resource", "about"]
+        .into_iter()
+        .find_map(|attr| scope.value().attr(attr));
+    if let Some(id) = id {
+        map.insert("@id".to_owned(), Json::String(state.attr(opt, "href", id)));
+    }
+    let mut props = vec![];
+    properties(scope, "property", "typeof", &mut props);
+    for prop in props {
+        let value = match prop.value().attr("typeof") {
+            Some(_) => rdfa_item(prop, opt, state),
+            None => rdfa_value(prop, opt, state),
+        };
+        for name in prop.value().attr("property").unwrap().split_whitespace() {
+            insert(&mut map, name.to_owned(), value.clone());
+        }
+    }
+    Json::Object(map)
+}
+
+/// The top-level items of RDFa, which are `typeof` not being a property.
+pub(crate) fn rdfa(elem: ElementRef, opt: &ExtractOptCompiled, state: &State) -> Vec<ExtractItem> {
+    let selector = Selector::parse("[typeof]:not([property])").unwrap();
+    elem.select(&selector)
+        .map(|scope| json_item(rdfa_item(scope, opt, state)))
+        .collect()
+}
+
+/// The `<meta>` whose attribute starts with the prefix, keyed by the rest of it.
+pub(crate) fn meta(
+    elem: ElementRef,
+    opt: &ExtractOptCompiled,
+    state: &State,
+    attrs: &[&str],
+    prefix: &str,
+) -> ExtractItem {
+    let selector = Selector::parse("meta[content]").unwrap();
+    let mut map = Map::new();
+    for meta in elem.select(&selector) {
+        let key = attrs
+            .iter()
+            .flat_map(|attr| meta.value().attr(attr))
+            .find_map(|x| x.trim().strip_prefix(prefix));
+        if let Some(key) = key {
+            let content = meta.value().attr("content").unwrap();
+            let value = if key.ends_with("url") || key.ends_with("image") {
+                state.attr(opt, "href", content)
+            } else {
+                content.to_owned()
+            };
+            insert(&mut map, key.to_owned(), Json::String(value));
+        }
+    }
+    json_item(Json::Object(map))
+}
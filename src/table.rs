@@ -0,0 +1,167 @@
+use crate::{Extract, ExtractItem, ExtractText, Pipeline, Transform, Value};
+use anyhow::Result;
+use indexmap::IndexMap;
+use scraper::ElementRef;
+use std::collections::HashMap;
+
+/// The option of `kind = "table"`
+pub struct TableCompiled {
+    /// Rename the headers, e.g. `{ "Product Name" = "name" }`
+    pub rename: HashMap<String, String>,
+    /// The transforms of the columns, keyed by the renamed header, applied to all the columns
+    /// it spans
+    pub transform: HashMap<String, Pipeline>,
+}
+
+impl TableCompiled {
+    pub(crate) fn compile(
+        rename: HashMap<String, String>,
+        transform: HashMap<String, Vec<Transform>>,
+    ) -> Result<Self> {
+        Ok(TableCompiled {
+            rename,
+            transform: transform
+                .into_iter()
+                .map(|(k, v)| Ok((k, Pipeline::compile(v)?)))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+fn child_elements<'a>(elem: ElementRef<'a>) -> impl Iterator<Item = ElementRef<'a>> {
+    elem.children().filter_map(ElementRef::wrap)
+}
+
+/// The rows of table, excluding the ones of nested tables, and whether they are in `<thead>`.
+fn rows(table: ElementRef) -> Vec<(ElementRef, bool)> {
+    let mut rows = vec![];
+    for child in child_elements(table) {
+        match child.value().name() {
+            "tr" => rows.push((child, false)),
+            section @ ("thead" | "tbody" | "tfoot") => rows.extend(
+                child_elements(child)
+                    .filter(|x| x.value().name() == "tr")
+                    .map(|x| (x, section == "thead")),
+            ),
+            _ => {}
+        }
+    }
+    rows
+}
+
+fn span(cell: ElementRef, attr: &str) -> usize {
+    cell.value()
+        .attr(attr)
+        .and_then(|x| x.trim().parse().ok())
+        .unwrap_or(1)
+        .clamp(1, 1000)
+}
+
+/// Lay out the cells on the grid with `colspan` and `rowspan`,
+/// return the rows of cells, and whether they are the header, which are all `<th>` or in `<thead>`.
+pub(crate) fn layout(table: ElementRef) -> Vec<(Vec<String>, bool)> {
+    // the rows left and the text of cells spanning down, indexed by column
+    let mut spans: Vec<(usize, String)> = vec![];
+    let mut grid = vec![];
+    for (row, in_head) in rows(table) {
+        let mut cells = vec![];
+        let mut all_th = true;
+        let mut any_cell = false;
+        let mut elems = child_elements(row).filter(|x| matches!(x.value().name(), "td" | "th"));
+        loop {
+            while let Some((left, text)) = spans.get_mut(cells.len()).filter(|x| x.0 > 0) {
+                *left -= 1;
+                cells.push(text.clone());
+            }
+            let cell = match elems.next() {
+                Some(cell) => cell,
+                None => break,
+            };
+            all_th &= cell.value().name() == "th";
+            any_cell = true;
+            let text = cell.text().collect::<String>();
+            let rowspan = span(cell, "rowspan");
+            for _ in 0..span(cell, "colspan") {
+                if spans.len() <= cells.len() {
+                    spans.resize(cells.len() + 1, (0, String::new()));
+                }
+                spans[cells.len()] = (rowspan - 1, text.clone());
+                cells.push(text.clone());
+            }
+        }
+        // the cells spanning down beyond the last cell of row
+        let len = cells.len();
+        for (col, (left, text)) in spans.iter_mut().enumerate().skip(len) {
+            if *left > 0 {
+                *left -= 1;
+                cells.resize(col, String::new());
+                cells.push(text.clone());
+            }
+        }
+        grid.push((cells, in_head || (any_cell && all_th)));
+    }
+    grid
+}
+
+/// Extract the records keyed by header from the table.
+pub(crate) fn extract_table(
+    elem: ElementRef,
+    table: &TableCompiled,
+    transform: &Pipeline,
+) -> Vec<ExtractItem> {
+    let mut grid = layout(elem)
+        .into_iter()
+        .filter(|x| !x.0.is_empty())
+        .peekable();
+    let mut headers = None;
+    while let Some((cells, _)) = grid.next_if(|x| x.1) {
+        headers = Some(cells);
+    }
+    // the first row is the header if no row is of `<th>` or in `<thead>`
+    let headers: Vec<_> = headers
+        .or_else(|| grid.next().map(|x| x.0))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|x| x.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+
+    grid.map(|(cells, _)| {
+        let mut items = IndexMap::new();
+        for (col, cell) in cells.into_iter().enumerate() {
+            let header = match headers.get(col).map(String::as_str) {
+                None | Some("") => col.to_string(),
+                Some(header) => header.to_owned(),
+            };
+            let mut header = table.rename.get(&header).cloned().unwrap_or(header);
+            let texts = table
+                .transform
+                .get(&header)
+                .unwrap_or(transform)
+                .apply(vec![cell]);
+            // the header spanning columns is suffixed by the order
+            if items.contains_key(&header) {
+                header = (2..)
+                    .map(|i| format!("{}_{}", header, i))
+                    .find(|x| !items.contains_key(x))
+                    .unwrap();
+            }
+            let text = match texts.len() {
+                0 => None,
+                1 => texts
+                    .into_iter()
+                    .next()
+                    .map(|x| ExtractText::One(Value::String(x))),
+                _ => Some(ExtractText::List(
+                    texts.into_iter().map(Value::String).collect(),
+                )),
+            };
+            let item = ExtractItem {
+                text,
+                items: IndexMap::new(),
+            };
+            items.insert(header, Extract::One(item));
+        }
+        ExtractItem { text: None, items }
+    })
+    .collect()
+}
@@ -0,0 +1,161 @@
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Node};
+
+/// The elements whose content is not rendered
+pub(crate) const SKIPPED: &[&str] = &["script", "style", "template", "noscript", "head"];
+
+/// The elements rendered in lines
+pub(crate) const BLOCKS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "caption",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "legend",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tr",
+    "ul",
+];
+
+fn is_cell(node: NodeRef<Node>) -> bool {
+    node.value()
+        .as_element()
+        .is_some_and(|x| matches!(x.name(), "td" | "th"))
+}
+
+/// Render the text like `innerText` of browsers.
+#[derive(Default)]
+struct Renderer {
+    out: String,
+    /// The collapsed whitespace waiting for the following text
+    space: bool,
+    /// The line breaks required by blocks waiting for the following text
+    breaks: usize,
+}
+
+impl Renderer {
+    fn flush(&mut self) {
+        if self.breaks > 0 {
+            if !self.out.is_empty() {
+                self.out.push_str(&"\n".repeat(self.breaks));
+            }
+        } else if self.space && !self.out.is_empty() && !self.out.ends_with(['\n', '\t']) {
+            self.out.push(' ');
+        }
+        self.breaks = 0;
+        self.space = false;
+    }
+
+    fn text(&mut self, text: &str, pre: bool) {
+        if pre {
+            self.flush();
+            self.out.push_str(text);
+            return;
+        }
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.space = true;
+            } else {
+                self.flush();
+                self.out.push(c);
+            }
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        self.space = false;
+        self.flush();
+        self.out.push(c);
+    }
+
+    fn render(&mut self, node: NodeRef<Node>, pre: bool) {
+        let elem = match node.value() {
+            Node::Text(text) => return self.text(text, pre),
+            Node::Element(elem) => elem,
+            _ => return,
+        };
+        let name = elem.name();
+        if SKIPPED.contains(&name) || elem.attr("hidden").is_some() {
+            return;
+        }
+        match name {
+            "br" => return self.push('\n'),
+            "td" | "th" if node.prev_siblings().any(is_cell) => self.push('\t'),
+            _ => {}
+        }
+
+        let breaks = match name {
+            "p" => 2,
+            name if BLOCKS.contains(&name) => 1,
+            _ => 0,
+        };
+        self.breaks = self.breaks.max(breaks);
+        for child in node.children() {
+            self.render(child, pre || name == "pre");
+        }
+        self.breaks = self.breaks.max(breaks);
+    }
+}
+
+/// The text rendered like `innerText` of browsers.
+pub(crate) fn inner_text(elem: ElementRef) -> String {
+    let pre = elem
+        .ancestors()
+        .chain(std::iter::once(*elem))
+        .any(|x| x.value().as_element().is_some_and(|x| x.name() == "pre"));
+    let mut renderer = Renderer::default();
+    for child in elem.children() {
+        renderer.render(child, pre);
+    }
+    renderer.out
+}
+
+/// Join the text nodes, which are trimmed and the blank ones are skipped if separator is given.
+pub(crate) fn join_text<'a>(
+    texts: impl Iterator<Item = &'a str>,
+    separator: Option<&str>,
+) -> String {
+    match separator {
+        None => texts.collect(),
+        Some(separator) => texts
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+            .join(separator),
+    }
+}
+
+/// The text nodes which are the direct children of element.
+pub(crate) fn own_text<'a>(elem: ElementRef<'a>) -> impl Iterator<Item = &'a str> {
+    elem.children()
+        .filter_map(|x| x.value().as_text())
+        .map(|x| &**x)
+}
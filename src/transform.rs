@@ -0,0 +1,133 @@
+use anyhow::Result;
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use serde::Deserialize;
+
+/// The step to post-process the text of target
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    /// Trim here, the text is always trimmed at first unless `no_trim` is given
+    Trim,
+    NoTrim,
+    Lowercase,
+    Uppercase,
+    CollapseWhitespace,
+    Replace {
+        pattern: String,
+        #[serde(default)]
+        with: String,
+    },
+    Split {
+        sep: String,
+    },
+    Join {
+        #[serde(default)]
+        sep: String,
+    },
+    StripPrefix(String),
+    UrlDecode,
+    HtmlUnescape,
+    /// The substring in chars, from `start` to `end` exclusively
+    Substring {
+        #[serde(default)]
+        start: usize,
+        #[serde(default)]
+        end: Option<usize>,
+    },
+}
+
+pub enum TransformCompiled {
+    Trim,
+    Lowercase,
+    Uppercase,
+    CollapseWhitespace,
+    Replace { pattern: Regex, with: String },
+    Split { sep: String },
+    Join { sep: String },
+    StripPrefix(String),
+    UrlDecode,
+    HtmlUnescape,
+    Substring { start: usize, end: Option<usize> },
+}
+
+/// The compiled list of transforms
+pub struct Pipeline {
+    pub trim: bool,
+    pub steps: Vec<TransformCompiled>,
+}
+
+impl Pipeline {
+    pub(crate) fn compile(transforms: Vec<Transform>) -> Result<Self> {
+        let mut trim = true;
+        let mut steps = vec![];
+        for transform in transforms {
+            steps.push(match transform {
+                Transform::NoTrim => {
+                    trim = false;
+                    continue;
+                }
+                Transform::Trim => TransformCompiled::Trim,
+                Transform::Lowercase => TransformCompiled::Lowercase,
+                Transform::Uppercase => TransformCompiled::Uppercase,
+                Transform::CollapseWhitespace => TransformCompiled::CollapseWhitespace,
+                Transform::Replace { pattern, with } => TransformCompiled::Replace {
+                    pattern: Regex::new(&pattern)?,
+                    with,
+                },
+                Transform::Split { sep } => TransformCompiled::Split { sep },
+                Transform::Join { sep } => TransformCompiled::Join { sep },
+                Transform::StripPrefix(prefix) => TransformCompiled::StripPrefix(prefix),
+                Transform::UrlDecode => TransformCompiled::UrlDecode,
+                Transform::HtmlUnescape => TransformCompiled::HtmlUnescape,
+                Transform::Substring { start, end } => TransformCompiled::Substring { start, end },
+            });
+        }
+        Ok(Pipeline { trim, steps })
+    }
+
+    pub(crate) fn apply(&self, mut texts: Vec<String>) -> Vec<String> {
+        if self.trim {
+            texts = texts.into_iter().map(|x| x.trim().to_owned()).collect();
+        }
+        for step in self.steps.iter() {
+            texts = match step {
+                TransformCompiled::Split { sep } => texts
+                    .iter()
+                    .flat_map(|x| x.split(sep.as_str()).map(|x| x.to_owned()))
+                    .collect(),
+                TransformCompiled::Join { sep } => vec![texts.join(sep)],
+                step => texts.into_iter().map(|x| step.apply(x)).collect(),
+            };
+        }
+        texts
+    }
+}
+
+impl TransformCompiled {
+    fn apply(&self, text: String) -> String {
+        match self {
+            Self::Trim => text.trim().to_owned(),
+            Self::Lowercase => text.to_lowercase(),
+            Self::Uppercase => text.to_uppercase(),
+            Self::CollapseWhitespace => text.split_whitespace().collect::<Vec<_>>().join(" "),
+            Self::Replace { pattern, with } => {
+                pattern.replace_all(&text, with.as_str()).into_owned()
+            }
+            Self::StripPrefix(prefix) => match text.strip_prefix(prefix.as_str()) {
+                Some(x) => x.to_owned(),
+                None => text,
+            },
+            Self::UrlDecode => percent_decode_str(&text).decode_utf8_lossy().into_owned(),
+            Self::HtmlUnescape => html_escape::decode_html_entities(&text).into_owned(),
+            Self::Substring { start, end } => {
+                let chars = text.chars().skip(*start);
+                match end {
+                    Some(end) => chars.take(end.saturating_sub(*start)).collect(),
+                    None => chars.collect(),
+                }
+            }
+            Self::Split { .. } | Self::Join { .. } => unreachable!(),
+        }
+    }
+}
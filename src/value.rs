@@ -0,0 +1,115 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// The value extracted from text
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Value {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// Serialized as a string to keep the precision
+    Decimal(Decimal),
+    Json(serde_json::Value),
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+                (Some(n), _) => Value::Int(n),
+                (None, Some(n)) => Value::Float(n),
+                (None, None) => Value::Json(serde_json::Value::Number(n)),
+            },
+            value => Value::Json(value),
+        }
+    }
+}
+
+/// The type which the extracted text is converted into
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueType {
+    String,
+    Int,
+    Float,
+    Bool,
+    Decimal,
+    /// Parsed by the `strftime`-like format if given, or as RFC 3339 otherwise
+    Datetime(Option<String>),
+    Json,
+}
+
+impl ValueType {
+    pub(crate) fn parse(ty: &str, format: Option<String>) -> Result<Self> {
+        if format.is_some() && ty != "datetime" {
+            bail!("`format` is only available for type `datetime`");
+        }
+        Ok(match ty {
+            "string" => Self::String,
+            "int" => Self::Int,
+            "float" => Self::Float,
+            "bool" => Self::Bool,
+            "decimal" => Self::Decimal,
+            "datetime" => Self::Datetime(format),
+            "json" => Self::Json,
+            ty => bail!("unknown type `{}`", ty),
+        })
+    }
+
+    pub(crate) fn convert(&self, text: &str) -> std::result::Result<Value, String> {
+        Ok(match self {
+            Self::String => Value::String(text.to_owned()),
+            Self::Int => Value::Int(text.parse().map_err(|e| format!("{}", e))?),
+            Self::Float => Value::Float(text.parse().map_err(|e| format!("{}", e))?),
+            Self::Bool => Value::Bool(match text.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => true,
+                "false" | "no" | "off" | "0" => false,
+                _ => return Err("invalid bool literal".to_owned()),
+            }),
+            Self::Decimal => Value::Decimal(Decimal::from_str(text).map_err(|e| format!("{}", e))?),
+            Self::Datetime(None) => Value::String(
+                DateTime::parse_from_rfc3339(text)
+                    .map_err(|e| format!("{}", e))?
+                    .to_rfc3339(),
+            ),
+            Self::Datetime(Some(format)) => Value::String(parse_datetime(text, format)?),
+            Self::Json => Value::Json(serde_json::from_str(text).map_err(|e| format!("{}", e))?),
+        })
+    }
+}
+
+/// Parse the datetime with the most precise form the format allows
+/// and render it in RFC 3339.
+fn parse_datetime(text: &str, format: &str) -> std::result::Result<String, String> {
+    if let Ok(datetime) = DateTime::parse_from_str(text, format) {
+        return Ok(datetime.to_rfc3339());
+    }
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(text, format) {
+        return Ok(datetime.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+    NaiveDate::parse_from_str(text, format)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .map_err(|e| format!("{}", e))
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String => write!(f, "string"),
+            Self::Int => write!(f, "int"),
+            Self::Float => write!(f, "float"),
+            Self::Bool => write!(f, "bool"),
+            Self::Decimal => write!(f, "decimal"),
+            Self::Datetime(None) => write!(f, "datetime"),
+            Self::Datetime(Some(format)) => write!(f, "datetime({})", format),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
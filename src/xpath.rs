@@ -0,0 +1,1203 @@
+//! A subset of XPath 1.0 evaluated over the tree of scraper.
+
+use anyhow::{anyhow, bail, Result};
+use ego_tree::{NodeId, NodeRef};
+use scraper::{ElementRef, Node};
+use std::collections::{HashMap, HashSet};
+use std::iter::once;
+
+/// The functions supported, with the minimum and maximum numbers of arguments
+const FUNCTIONS: &[(&str, usize, usize)] = &[
+    ("position", 0, 0),
+    ("last", 0, 0),
+    ("count", 1, 1),
+    ("string", 0, 1),
+    ("contains", 2, 2),
+    ("starts-with", 2, 2),
+    ("ends-with", 2, 2),
+    ("normalize-space", 0, 1),
+    ("string-length", 0, 1),
+    ("concat", 2, usize::MAX),
+    ("substring", 2, 3),
+    ("substring-before", 2, 2),
+    ("substring-after", 2, 2),
+    ("translate", 3, 3),
+    ("name", 0, 1),
+    ("local-name", 0, 1),
+    ("not", 1, 1),
+    ("true", 0, 0),
+    ("false", 0, 0),
+    ("boolean", 1, 1),
+    ("number", 0, 1),
+    ("sum", 1, 1),
+    ("floor", 1, 1),
+    ("ceiling", 1, 1),
+    ("round", 1, 1),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Dot,
+    DotDot,
+    At,
+    Comma,
+    ColonColon,
+    Pipe,
+    Plus,
+    Minus,
+    Eq,
+    Neq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `*` as the name test
+    Star,
+    /// `*` as the operator
+    Mul,
+    And,
+    Or,
+    Div,
+    Mod,
+    Literal(String),
+    Number(f64),
+    Name(String),
+}
+
+impl Token {
+    /// Whether the `*` or name following is an operator
+    fn precedes_operator(&self) -> bool {
+        !matches!(
+            self,
+            Token::Slash
+                | Token::DoubleSlash
+                | Token::LParen
+                | Token::LBracket
+                | Token::At
+                | Token::Comma
+                | Token::ColonColon
+                | Token::Pipe
+                | Token::Plus
+                | Token::Minus
+                | Token::Eq
+                | Token::Neq
+                | Token::Lt
+                | Token::Le
+                | Token::Gt
+                | Token::Ge
+                | Token::Mul
+                | Token::And
+                | Token::Or
+                | Token::Div
+                | Token::Mod
+        )
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<_> = input.chars().collect();
+    let mut tokens: Vec<Token> = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let operator = tokens.last().is_some_and(Token::precedes_operator);
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '/' if next == Some('/') => {
+                i += 1;
+                Token::DoubleSlash
+            }
+            '/' => Token::Slash,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '@' => Token::At,
+            ',' => Token::Comma,
+            '|' => Token::Pipe,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '=' => Token::Eq,
+            '!' if next == Some('=') => {
+                i += 1;
+                Token::Neq
+            }
+            '<' if next == Some('=') => {
+                i += 1;
+                Token::Le
+            }
+            '<' => Token::Lt,
+            '>' if next == Some('=') => {
+                i += 1;
+                Token::Ge
+            }
+            '>' => Token::Gt,
+            ':' if next == Some(':') => {
+                i += 1;
+                Token::ColonColon
+            }
+            '*' if operator => Token::Mul,
+            '*' => Token::Star,
+            '.' if next == Some('.') => {
+                i += 1;
+                Token::DotDot
+            }
+            '.' if !next.is_some_and(|x| x.is_ascii_digit()) => Token::Dot,
+            '"' | '\'' => {
+                let len = chars[i + 1..]
+                    .iter()
+                    .position(|&x| x == c)
+                    .ok_or_else(|| anyhow!("unclosed literal"))?;
+                let literal = chars[i + 1..i + 1 + len].iter().collect();
+                i += len + 1;
+                Token::Literal(literal)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|&&x| x.is_ascii_digit() || x == '.')
+                    .count();
+                let number: String = chars[i..i + len].iter().collect();
+                i += len - 1;
+                Token::Number(number.parse()?)
+            }
+            c if is_name_start(c) => {
+                let len = chars[i..].iter().take_while(|&&x| is_name_char(x)).count();
+                let name: String = chars[i..i + len].iter().collect();
+                i += len - 1;
+                match name.as_str() {
+                    "and" if operator => Token::And,
+                    "or" if operator => Token::Or,
+                    "div" if operator => Token::Div,
+                    "mod" if operator => Token::Mod,
+                    _ => Token::Name(name),
+                }
+            }
+            c => bail!("unexpected char `{}`", c),
+        };
+        tokens.push(token);
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Parent,
+    Ancestor,
+    AncestorOrSelf,
+    FollowingSibling,
+    PrecedingSibling,
+    Following,
+    Preceding,
+    Self_,
+    Attribute,
+}
+
+impl Axis {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "child" => Axis::Child,
+            "descendant" => Axis::Descendant,
+            "descendant-or-self" => Axis::DescendantOrSelf,
+            "parent" => Axis::Parent,
+            "ancestor" => Axis::Ancestor,
+            "ancestor-or-self" => Axis::AncestorOrSelf,
+            "following-sibling" => Axis::FollowingSibling,
+            "preceding-sibling" => Axis::PrecedingSibling,
+            "following" => Axis::Following,
+            "preceding" => Axis::Preceding,
+            "self" => Axis::Self_,
+            "attribute" => Axis::Attribute,
+            name => bail!("unknown axis `{}`", name),
+        })
+    }
+
+    /// Whether the nodes selected from the nodes in document order are still in document order,
+    /// the children of nested nodes are not unless there is only one node.
+    fn keeps_order(&self, single: bool) -> bool {
+        match self {
+            Axis::Descendant | Axis::DescendantOrSelf | Axis::Self_ => true,
+            Axis::Child | Axis::Attribute => single,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum NodeTest {
+    /// `*`
+    Any,
+    Name(String),
+    /// `node()`
+    Node,
+    /// `text()`
+    Text,
+    /// `comment()`
+    Comment,
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+impl Step {
+    fn descendant_or_self() -> Self {
+        Step {
+            axis: Axis::DescendantOrSelf,
+            test: NodeTest::Node,
+            predicates: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Eq,
+    Neq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    /// The location path, absolute or not
+    Path(bool, Vec<Step>),
+    /// The primary expression with predicates, followed by the relative location path
+    Filter(Box<Expr>, Vec<Expr>, Vec<Step>),
+    Literal(String),
+    Number(f64),
+    Call(String, Vec<Expr>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + n)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<()> {
+        if self.eat(&token) {
+            Ok(())
+        } else {
+            bail!("expect {:?}, found {:?}", token, self.peek())
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.and()?;
+        while self.eat(&Token::Or) {
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut lhs = self.equality()?;
+        while self.eat(&Token::And) {
+            lhs = Expr::And(Box::new(lhs), Box::new(self.equality()?));
+        }
+        Ok(lhs)
+    }
+
+    fn equality(&mut self) -> Result<Expr> {
+        let mut lhs = self.relational()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Eq) => BinOp::Eq,
+                Some(Token::Neq) => BinOp::Neq,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.relational()?));
+        }
+    }
+
+    fn relational(&mut self) -> Result<Expr> {
+        let mut lhs = self.additive()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Lt) => BinOp::Lt,
+                Some(Token::Le) => BinOp::Le,
+                Some(Token::Gt) => BinOp::Gt,
+                Some(Token::Ge) => BinOp::Ge,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.additive()?));
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expr> {
+        let mut lhs = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinOp::Add,
+                Some(Token::Minus) => BinOp::Sub,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Mul) => BinOp::Mul,
+                Some(Token::Div) => BinOp::Div,
+                Some(Token::Mod) => BinOp::Mod,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat(&Token::Minus) {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        let mut lhs = self.path()?;
+        while self.eat(&Token::Pipe) {
+            lhs = Expr::Union(Box::new(lhs), Box::new(self.path()?));
+        }
+        Ok(lhs)
+    }
+
+    fn path(&mut self) -> Result<Expr> {
+        let filter = match (self.peek(), self.peek_at(1)) {
+            (Some(Token::Literal(_) | Token::Number(_) | Token::LParen), _) => true,
+            (Some(Token::Name(name)), Some(Token::LParen)) => {
+                !matches!(name.as_str(), "node" | "text" | "comment")
+            }
+            _ => false,
+        };
+        if !filter {
+            return self.location_path();
+        }
+
+        let primary = self.primary()?;
+        let predicates = self.predicates()?;
+        let mut steps = vec![];
+        self.steps(&mut steps)?;
+        if predicates.is_empty() && steps.is_empty() {
+            Ok(primary)
+        } else {
+            Ok(Expr::Filter(Box::new(primary), predicates, steps))
+        }
+    }
+
+    fn location_path(&mut self) -> Result<Expr> {
+        let mut steps = vec![];
+        let absolute = match self.peek() {
+            Some(Token::Slash) => {
+                self.pos += 1;
+                if !self.starts_step() {
+                    return Ok(Expr::Path(true, steps));
+                }
+                true
+            }
+            Some(Token::DoubleSlash) => {
+                self.pos += 1;
+                steps.push(Step::descendant_or_self());
+                true
+            }
+            _ => false,
+        };
+        steps.push(self.step()?);
+        self.steps(&mut steps)?;
+        Ok(Expr::Path(absolute, steps))
+    }
+
+    fn starts_step(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token::Dot | Token::DotDot | Token::At | Token::Star | Token::Name(_))
+        )
+    }
+
+    fn steps(&mut self, steps: &mut Vec<Step>) -> Result<()> {
+        loop {
+            match self.peek() {
+                Some(Token::Slash) => self.pos += 1,
+                Some(Token::DoubleSlash) => {
+                    self.pos += 1;
+                    steps.push(Step::descendant_or_self());
+                }
+                _ => return Ok(()),
+            }
+            steps.push(self.step()?);
+        }
+    }
+
+    fn step(&mut self) -> Result<Step> {
+        let (axis, test) = if self.eat(&Token::Dot) {
+            (Axis::Self_, NodeTest::Node)
+        } else if self.eat(&Token::DotDot) {
+            (Axis::Parent, NodeTest::Node)
+        } else {
+            let axis = match (self.peek(), self.peek_at(1)) {
+                (Some(Token::At), _) => {
+                    self.pos += 1;
+                    Axis::Attribute
+                }
+                (Some(Token::Name(name)), Some(Token::ColonColon)) => {
+                    let axis = Axis::parse(name)?;
+                    self.pos += 2;
+                    axis
+                }
+                _ => Axis::Child,
+            };
+            let test = match self.bump() {
+                Some(Token::Star) => NodeTest::Any,
+                Some(Token::Name(name)) if self.peek() == Some(&Token::LParen) => {
+                    self.pos += 1;
+                    self.expect(Token::RParen)?;
+                    match name.as_str() {
+                        "node" => NodeTest::Node,
+                        "text" => NodeTest::Text,
+                        "comment" => NodeTest::Comment,
+                        name => bail!("unknown node type `{}`", name),
+                    }
+                }
+                Some(Token::Name(name)) => NodeTest::Name(name.to_ascii_lowercase()),
+                token => bail!("expect node test, found {:?}", token),
+            };
+            (axis, test)
+        };
+        Ok(Step {
+            axis,
+            test,
+            predicates: self.predicates()?,
+        })
+    }
+
+    fn predicates(&mut self) -> Result<Vec<Expr>> {
+        let mut predicates = vec![];
+        while self.eat(&Token::LBracket) {
+            predicates.push(self.expr()?);
+            self.expect(Token::RBracket)?;
+        }
+        Ok(predicates)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.bump() {
+            Some(Token::LParen) => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Literal(literal)) => Ok(Expr::Literal(literal)),
+            Some(Token::Number(number)) => Ok(Expr::Number(number)),
+            Some(Token::Name(name)) => {
+                if !FUNCTIONS.iter().any(|x| x.0 == name) {
+                    bail!("unknown function `{}`", name);
+                }
+                self.expect(Token::LParen)?;
+                let mut args = vec![];
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        self.expect(Token::Comma)?;
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            token => bail!("unexpected token {:?}", token),
+        }
+    }
+}
+
+/// The node in XPath, which may be an attribute
+#[derive(Clone, Copy)]
+enum XNode<'a> {
+    Node(NodeRef<'a, Node>),
+    Attr(NodeRef<'a, Node>, &'a str, &'a str),
+}
+
+impl<'a> XNode<'a> {
+    fn key(&self) -> (NodeId, Option<&'a str>) {
+        match self {
+            XNode::Node(node) => (node.id(), None),
+            XNode::Attr(owner, name, _) => (owner.id(), Some(*name)),
+        }
+    }
+
+    fn owner(&self) -> NodeRef<'a, Node> {
+        match self {
+            XNode::Node(node) | XNode::Attr(node, ..) => *node,
+        }
+    }
+
+    fn string(&self) -> String {
+        match self {
+            XNode::Attr(_, _, value) => value.to_string(),
+            XNode::Node(node) => match node.value() {
+                Node::Text(text) => (**text).to_owned(),
+                Node::Comment(comment) => (**comment).to_owned(),
+                _ => node
+                    .descendants()
+                    .filter_map(|x| x.value().as_text())
+                    .map(|x| &**x)
+                    .collect(),
+            },
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            XNode::Attr(_, name, _) => name.to_string(),
+            XNode::Node(node) => match node.value() {
+                Node::Element(elem) => elem.name().to_owned(),
+                _ => String::new(),
+            },
+        }
+    }
+
+    fn matches(&self, test: &NodeTest) -> bool {
+        match self {
+            XNode::Attr(_, name, _) => match test {
+                NodeTest::Any | NodeTest::Node => true,
+                NodeTest::Name(test) => name.eq_ignore_ascii_case(test),
+                _ => false,
+            },
+            XNode::Node(node) => match (node.value(), test) {
+                (_, NodeTest::Node) => true,
+                (Node::Element(_), NodeTest::Any) => true,
+                (Node::Element(elem), NodeTest::Name(name)) => {
+                    elem.name().eq_ignore_ascii_case(name)
+                }
+                (Node::Text(_), NodeTest::Text) => true,
+                (Node::Comment(_), NodeTest::Comment) => true,
+                _ => false,
+            },
+        }
+    }
+
+    fn axis(self, axis: Axis) -> Vec<XNode<'a>> {
+        let node = match self {
+            XNode::Node(node) => node,
+            XNode::Attr(owner, ..) => {
+                return match axis {
+                    Axis::Parent => vec![XNode::Node(owner)],
+                    Axis::Ancestor => once(owner)
+                        .chain(owner.ancestors())
+                        .map(XNode::Node)
+                        .collect(),
+                    Axis::AncestorOrSelf => once(self)
+                        .chain(once(owner).chain(owner.ancestors()).map(XNode::Node))
+                        .collect(),
+                    Axis::Self_ => vec![self],
+                    _ => vec![],
+                };
+            }
+        };
+        let nodes: Vec<_> = match axis {
+            Axis::Child => node.children().collect(),
+            Axis::Descendant => node.descendants().skip(1).collect(),
+            Axis::DescendantOrSelf => node.descendants().collect(),
+            Axis::Parent => node.parent().into_iter().collect(),
+            Axis::Ancestor => node.ancestors().collect(),
+            Axis::AncestorOrSelf => once(node).chain(node.ancestors()).collect(),
+            Axis::FollowingSibling => node.next_siblings().collect(),
+            Axis::PrecedingSibling => node.prev_siblings().collect(),
+            Axis::Following => once(node)
+                .chain(node.ancestors())
+                .flat_map(|x| x.next_siblings())
+                .flat_map(|x| x.descendants())
+                .collect(),
+            Axis::Preceding => once(node)
+                .chain(node.ancestors())
+                .flat_map(|x| x.prev_siblings())
+                .flat_map(|x| x.descendants().collect::<Vec<_>>().into_iter().rev())
+                .collect(),
+            Axis::Self_ => vec![node],
+            Axis::Attribute => {
+                return match node.value() {
+                    Node::Element(elem) => elem
+                        .attrs()
+                        .map(|(name, value)| XNode::Attr(node, name, value))
+                        .collect(),
+                    _ => vec![],
+                };
+            }
+        };
+        nodes.into_iter().map(XNode::Node).collect()
+    }
+}
+
+enum XValue<'a> {
+    Nodes(Vec<XNode<'a>>),
+    Str(String),
+    Num(f64),
+    Bool(bool),
+}
+
+impl XValue<'_> {
+    fn boolean(&self) -> bool {
+        match self {
+            XValue::Nodes(nodes) => !nodes.is_empty(),
+            XValue::Str(s) => !s.is_empty(),
+            XValue::Num(n) => *n != 0.0 && !n.is_nan(),
+            XValue::Bool(b) => *b,
+        }
+    }
+
+    fn number(&self) -> f64 {
+        match self {
+            XValue::Num(n) => *n,
+            XValue::Bool(b) => f64::from(u8::from(*b)),
+            value => value.string().trim().parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    fn string(&self) -> String {
+        match self {
+            XValue::Nodes(nodes) => nodes.first().map(XNode::string).unwrap_or_default(),
+            XValue::Str(s) => s.clone(),
+            XValue::Num(n) if n.is_finite() && n.fract() == 0.0 => format!("{}", *n as i64),
+            XValue::Num(n) => format!("{}", n),
+            XValue::Bool(b) => format!("{}", b),
+        }
+    }
+}
+
+fn compare(op: BinOp, lhs: &XValue, rhs: &XValue) -> bool {
+    match (lhs, rhs) {
+        (XValue::Nodes(_), XValue::Bool(_)) | (XValue::Bool(_), XValue::Nodes(_)) => compare(
+            op,
+            &XValue::Bool(lhs.boolean()),
+            &XValue::Bool(rhs.boolean()),
+        ),
+        (XValue::Nodes(nodes), rhs) => nodes
+            .iter()
+            .any(|x| compare(op, &XValue::Str(x.string()), rhs)),
+        (lhs, XValue::Nodes(nodes)) => nodes
+            .iter()
+            .any(|x| compare(op, lhs, &XValue::Str(x.string()))),
+        (lhs, rhs) => match op {
+            BinOp::Eq | BinOp::Neq => {
+                let eq = match (lhs, rhs) {
+                    (XValue::Bool(_), _) | (_, XValue::Bool(_)) => lhs.boolean() == rhs.boolean(),
+                    (XValue::Num(_), _) | (_, XValue::Num(_)) => lhs.number() == rhs.number(),
+                    _ => lhs.string() == rhs.string(),
+                };
+                eq == (op == BinOp::Eq)
+            }
+            BinOp::Lt => lhs.number() < rhs.number(),
+            BinOp::Le => lhs.number() <= rhs.number(),
+            BinOp::Gt => lhs.number() > rhs.number(),
+            BinOp::Ge => lhs.number() >= rhs.number(),
+            _ => unreachable!(),
+        },
+    }
+}
+
+struct Context<'a> {
+    node: XNode<'a>,
+    position: usize,
+    size: usize,
+}
+
+struct Evaluator<'a> {
+    root: NodeRef<'a, Node>,
+    /// The position of nodes in document order, built lazily
+    order: Option<HashMap<NodeId, usize>>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn eval(&mut self, expr: &Expr, ctx: &Context<'a>) -> Result<XValue<'a>> {
+        Ok(match expr {
+            Expr::Or(lhs, rhs) => {
+                XValue::Bool(self.eval(lhs, ctx)?.boolean() || self.eval(rhs, ctx)?.boolean())
+            }
+            Expr::And(lhs, rhs) => {
+                XValue::Bool(self.eval(lhs, ctx)?.boolean() && self.eval(rhs, ctx)?.boolean())
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs, ctx)?;
+                let rhs = self.eval(rhs, ctx)?;
+                match op {
+                    BinOp::Add => XValue::Num(lhs.number() + rhs.number()),
+                    BinOp::Sub => XValue::Num(lhs.number() - rhs.number()),
+                    BinOp::Mul => XValue::Num(lhs.number() * rhs.number()),
+                    BinOp::Div => XValue::Num(lhs.number() / rhs.number()),
+                    BinOp::Mod => XValue::Num(lhs.number() % rhs.number()),
+                    op => XValue::Bool(compare(*op, &lhs, &rhs)),
+                }
+            }
+            Expr::Neg(expr) => XValue::Num(-self.eval(expr, ctx)?.number()),
+            Expr::Union(lhs, rhs) => match (self.eval(lhs, ctx)?, self.eval(rhs, ctx)?) {
+                (XValue::Nodes(mut lhs), XValue::Nodes(rhs)) => {
+                    lhs.extend(rhs);
+                    XValue::Nodes(self.sort(lhs))
+                }
+                _ => bail!("the operands of union should be node-sets"),
+            },
+            Expr::Path(absolute, steps) => {
+                let start = if *absolute {
+                    XNode::Node(self.root)
+                } else {
+                    ctx.node
+                };
+                XValue::Nodes(self.steps(vec![start], steps)?)
+            }
+            Expr::Filter(primary, predicates, steps) => match self.eval(primary, ctx)? {
+                XValue::Nodes(nodes) => {
+                    let nodes = self.predicates(nodes, predicates)?;
+                    XValue::Nodes(self.steps(nodes, steps)?)
+                }
+                _ => bail!("the predicates and steps should follow a node-set"),
+            },
+            Expr::Literal(literal) => XValue::Str(literal.clone()),
+            Expr::Number(number) => XValue::Num(*number),
+            Expr::Call(name, args) => self.call(name, args, ctx)?,
+        })
+    }
+
+    fn steps(&mut self, mut nodes: Vec<XNode<'a>>, steps: &[Step]) -> Result<Vec<XNode<'a>>> {
+        for step in steps {
+            let mut selected = vec![];
+            for node in nodes.iter() {
+                let candidates = node
+                    .axis(step.axis)
+                    .into_iter()
+                    .filter(|x| x.matches(&step.test))
+                    .collect();
+                selected.extend(self.predicates(candidates, &step.predicates)?);
+            }
+            nodes = if step.axis.keeps_order(nodes.len() <= 1) {
+                dedup(selected)
+            } else {
+                self.sort(selected)
+            };
+        }
+        Ok(nodes)
+    }
+
+    fn predicates(
+        &mut self,
+        mut nodes: Vec<XNode<'a>>,
+        predicates: &[Expr],
+    ) -> Result<Vec<XNode<'a>>> {
+        for predicate in predicates {
+            let size = nodes.len();
+            let mut selected = vec![];
+            for (i, node) in nodes.into_iter().enumerate() {
+                let ctx = Context {
+                    node,
+                    position: i + 1,
+                    size,
+                };
+                let keep = match self.eval(predicate, &ctx)? {
+                    XValue::Num(n) => n == (i + 1) as f64,
+                    value => value.boolean(),
+                };
+                if keep {
+                    selected.push(node);
+                }
+            }
+            nodes = selected;
+        }
+        Ok(nodes)
+    }
+
+    /// Deduplicate and sort the nodes in document order.
+    fn sort(&mut self, nodes: Vec<XNode<'a>>) -> Vec<XNode<'a>> {
+        let root = self.root;
+        let order = self.order.get_or_insert_with(|| {
+            root.descendants()
+                .enumerate()
+                .map(|(i, x)| (x.id(), i))
+                .collect()
+        });
+        let mut nodes = dedup(nodes);
+        nodes.sort_by_key(|x| {
+            (
+                order.get(&x.owner().id()).copied(),
+                matches!(x, XNode::Attr(..)),
+            )
+        });
+        nodes
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], ctx: &Context<'a>) -> Result<XValue<'a>> {
+        let values = args
+            .iter()
+            .map(|x| self.eval(x, ctx))
+            .collect::<Result<Vec<_>>>()?;
+        let string = |i: usize| {
+            values
+                .get(i)
+                .map_or_else(|| ctx.node.string(), XValue::string)
+        };
+        Ok(match (name, values.len()) {
+            ("position", 0) => XValue::Num(ctx.position as f64),
+            ("last", 0) => XValue::Num(ctx.size as f64),
+            ("count", 1) => match &values[0] {
+                XValue::Nodes(nodes) => XValue::Num(nodes.len() as f64),
+                _ => bail!("count() requires a node-set"),
+            },
+            ("string", 0 | 1) => XValue::Str(string(0)),
+            ("contains", 2) => XValue::Bool(string(0).contains(&string(1))),
+            ("starts-with", 2) => XValue::Bool(string(0).starts_with(&string(1))),
+            ("ends-with", 2) => XValue::Bool(string(0).ends_with(&string(1))),
+            ("normalize-space", 0 | 1) => {
+                XValue::Str(string(0).split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            ("string-length", 0 | 1) => XValue::Num(string(0).chars().count() as f64),
+            ("concat", n) if n >= 2 => XValue::Str(values.iter().map(XValue::string).collect()),
+            ("substring", 2 | 3) => {
+                let start = values[1].number().round();
+                let end = values
+                    .get(2)
+                    .map_or(f64::INFINITY, |x| start + x.number().round());
+                XValue::Str(
+                    string(0)
+                        .chars()
+                        .enumerate()
+                        .filter(|(i, _)| {
+                            let position = (i + 1) as f64;
+                            position >= start && position < end
+                        })
+                        .map(|(_, c)| c)
+                        .collect(),
+                )
+            }
+            ("substring-before", 2) => {
+                let s = string(0);
+                XValue::Str(s.split_once(&string(1)).map_or("", |x| x.0).to_owned())
+            }
+            ("substring-after", 2) => {
+                let s = string(0);
+                XValue::Str(s.split_once(&string(1)).map_or("", |x| x.1).to_owned())
+            }
+            ("translate", 3) => {
+                let from: Vec<_> = string(1).chars().collect();
+                let to: Vec<_> = string(2).chars().collect();
+                XValue::Str(
+                    string(0)
+                        .chars()
+                        .flat_map(|c| match from.iter().position(|&x| x == c) {
+                            Some(i) => to.get(i).copied(),
+                            None => Some(c),
+                        })
+                        .collect(),
+                )
+            }
+            ("name" | "local-name", 0) => XValue::Str(ctx.node.name()),
+            ("name" | "local-name", 1) => match &values[0] {
+                XValue::Nodes(nodes) => {
+                    XValue::Str(nodes.first().map(XNode::name).unwrap_or_default())
+                }
+                _ => bail!("{}() requires a node-set", name),
+            },
+            ("not", 1) => XValue::Bool(!values[0].boolean()),
+            ("true", 0) => XValue::Bool(true),
+            ("false", 0) => XValue::Bool(false),
+            ("boolean", 1) => XValue::Bool(values[0].boolean()),
+            ("number", 0 | 1) => XValue::Num(XValue::Str(string(0)).number()),
+            ("sum", 1) => match &values[0] {
+                XValue::Nodes(nodes) => {
+                    XValue::Num(nodes.iter().map(|x| XValue::Str(x.string()).number()).sum())
+                }
+                _ => bail!("sum() requires a node-set"),
+            },
+            ("floor", 1) => XValue::Num(values[0].number().floor()),
+            ("ceiling", 1) => XValue::Num(values[0].number().ceil()),
+            ("round", 1) => XValue::Num(values[0].number().round()),
+            (name, n) => bail!("function `{}` does not accept {} arguments", name, n),
+        })
+    }
+}
+
+/// Whether the expression is evaluated as a node-set
+fn is_nodes(expr: &Expr) -> bool {
+    matches!(expr, Expr::Path(..) | Expr::Union(..) | Expr::Filter(..))
+}
+
+/// Check the numbers of arguments and the operands requiring node-sets, so that the evaluation
+/// does not fail.
+fn check(expr: &Expr) -> Result<()> {
+    match expr {
+        Expr::Or(lhs, rhs) | Expr::And(lhs, rhs) | Expr::Binary(_, lhs, rhs) => {
+            check(lhs)?;
+            check(rhs)
+        }
+        Expr::Neg(expr) => check(expr),
+        Expr::Union(lhs, rhs) => {
+            if !is_nodes(lhs) || !is_nodes(rhs) {
+                bail!("the operands of union should be node-sets");
+            }
+            check(lhs)?;
+            check(rhs)
+        }
+        Expr::Path(_, steps) => steps.iter().flat_map(|x| &x.predicates).try_for_each(check),
+        Expr::Filter(primary, predicates, steps) => {
+            if !is_nodes(primary) {
+                bail!("the predicates and steps should follow a node-set");
+            }
+            check(primary)?;
+            predicates
+                .iter()
+                .chain(steps.iter().flat_map(|x| &x.predicates))
+                .try_for_each(check)
+        }
+        Expr::Literal(_) | Expr::Number(_) => Ok(()),
+        Expr::Call(name, args) => {
+            let (_, min, max) = FUNCTIONS.iter().find(|x| x.0 == name).unwrap();
+            if args.len() < *min || args.len() > *max {
+                bail!(
+                    "function `{}` does not accept {} arguments",
+                    name,
+                    args.len()
+                );
+            }
+            let nodes = matches!(name.as_str(), "count" | "sum" | "name" | "local-name");
+            if nodes && !args.first().is_none_or(is_nodes) {
+                bail!("{}() requires a node-set", name);
+            }
+            args.iter().try_for_each(check)
+        }
+    }
+}
+
+fn dedup(nodes: Vec<XNode>) -> Vec<XNode> {
+    let mut keys = HashSet::new();
+    nodes.into_iter().filter(|x| keys.insert(x.key())).collect()
+}
+
+/// The compiled XPath which selects elements
+#[derive(Debug, Clone)]
+pub struct XPath {
+    expr: Expr,
+}
+
+impl XPath {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+        };
+        let expr = parser.expr()?;
+        if let Some(token) = parser.peek() {
+            bail!("unexpected token {:?}", token);
+        }
+        if !is_nodes(&expr) {
+            bail!("xpath `{}` does not select elements", input);
+        }
+        check(&expr)?;
+        Ok(XPath { expr })
+    }
+
+    /// Select the elements relative to the element, the others like attributes are ignored.
+    pub fn select<'a>(&self, elem: ElementRef<'a>) -> Vec<ElementRef<'a>> {
+        let node = *elem;
+        let mut evaluator = Evaluator {
+            root: node.ancestors().last().unwrap_or(node),
+            order: None,
+        };
+        let ctx = Context {
+            node: XNode::Node(node),
+            position: 1,
+            size: 1,
+        };
+        // the expression is checked by `parse`, so it's evaluated as a node-set without error
+        match evaluator.eval(&self.expr, &ctx) {
+            Ok(XValue::Nodes(nodes)) => nodes
+                .into_iter()
+                .filter_map(|x| match x {
+                    XNode::Node(node) => ElementRef::wrap(node),
+                    XNode::Attr(..) => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scraper::Html;
+
+    fn select(html: &str, xpath: &str) -> Vec<String> {
+        let html = Html::parse_fragment(html);
+        XPath::parse(xpath)
+            .unwrap()
+            .select(html.root_element())
+            .into_iter()
+            .map(|x| x.text().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        use Token::*;
+        let name = |x: &str| Name(x.to_owned());
+        assert_eq!(
+            tokenize("//*[@*]").unwrap(),
+            [DoubleSlash, Star, LBracket, At, Star, RBracket]
+        );
+        assert_eq!(
+            tokenize("div * 2").unwrap(),
+            [name("div"), Mul, Number(2.0)]
+        );
+        assert_eq!(
+            tokenize("div div mod or and").unwrap(),
+            [name("div"), Div, name("mod"), Or, name("and")]
+        );
+        assert_eq!(
+            tokenize("a[. != 'x' and .5 <= last()]").unwrap(),
+            [
+                name("a"),
+                LBracket,
+                Dot,
+                Neq,
+                Literal("x".to_owned()),
+                And,
+                Number(0.5),
+                Le,
+                name("last"),
+                LParen,
+                RParen,
+                RBracket
+            ]
+        );
+        assert_eq!(
+            tokenize("../following-sibling::a").unwrap(),
+            [
+                DotDot,
+                Slash,
+                name("following-sibling"),
+                ColonColon,
+                name("a")
+            ]
+        );
+        assert!(tokenize("a[text() = 'x]").is_err());
+        assert!(tokenize("a#b").is_err());
+    }
+
+    #[test]
+    fn test_axes() {
+        let html = r#"<div id="a"><p>1</p><div><p>2</p></div><p>3</p></div><p>4</p>"#;
+        assert_eq!(select(html, "//div/p"), ["1", "2", "3"]);
+        assert_eq!(select(html, "//p[. = '2']/ancestor::div/@id/.."), ["123"]);
+        assert_eq!(select(html, "//p[. = '2']/../.."), ["123"]);
+        assert_eq!(
+            select(html, "//p[. = '1']/following-sibling::*"),
+            ["2", "3"]
+        );
+        assert_eq!(select(html, "//p[. = '3']/preceding-sibling::p"), ["1"]);
+        assert_eq!(select(html, "//p[. = '2']/following::p"), ["3", "4"]);
+        assert_eq!(select(html, "//p[. = '3']/preceding::p"), ["1", "2"]);
+        assert_eq!(select(html, "//div[@id]/descendant::p"), ["1", "2", "3"]);
+        assert_eq!(select(html, "//p/self::p[. > 2]"), ["3", "4"]);
+        assert_eq!(select(html, "//p[. = '4'] | //p[. = '1']"), ["1", "4"]);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let html = r#"<ul><li class="x">a</li><li>b</li><li class="x">c</li></ul>"#;
+        assert_eq!(select(html, "//li[2]"), ["b"]);
+        assert_eq!(select(html, "//li[last()]"), ["c"]);
+        assert_eq!(select(html, "//li[position() < 3]"), ["a", "b"]);
+        assert_eq!(select(html, "//li[@class = 'x'][2]"), ["c"]);
+        assert_eq!(select(html, "(//li)[not(@class)]"), ["b"]);
+        assert_eq!(select(html, "//ul[count(li) = 3]/li[1]"), ["a"]);
+    }
+
+    #[test]
+    fn test_functions() {
+        let html = r#"<a href="/x"> Hello  World </a><a href="/y">Bye</a>"#;
+        assert_eq!(
+            select(html, "//a[contains(., 'World')]"),
+            [" Hello  World "]
+        );
+        assert_eq!(
+            select(html, "//a[normalize-space() = 'Hello World']").len(),
+            1
+        );
+        assert_eq!(select(html, "//a[starts-with(@href, '/y')]"), ["Bye"]);
+        assert_eq!(select(html, "//a[substring(., 2, 2) = 'ye']"), ["Bye"]);
+        assert_eq!(
+            select(html, "//a[translate(., 'Bye', 'bYE') = 'bYE']"),
+            ["Bye"]
+        );
+        assert_eq!(select(html, "//a[concat(@href, '!') = '/x!']").len(), 1);
+        assert_eq!(select(html, "//a[string-length() = 3]"), ["Bye"]);
+        assert_eq!(select(html, "//*[name() = 'a'][2]"), ["Bye"]);
+
+        assert!(XPath::parse("//li[count(1)]").is_err());
+        assert!(XPath::parse("//li[sum('a') > 1]").is_err());
+        assert!(XPath::parse("//li[contains(.)]").is_err());
+        assert!(XPath::parse("//li[unknown()]").is_err());
+        assert!(XPath::parse("'a' | //li").is_err());
+        assert!(XPath::parse("count(//li)").is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! The derive macro of [sthe](https://crates.io/crates/sthe).
+
+mod opt;
+mod table;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+/// Implement `sthe::FromHtml` for the struct, the type should also implement `Deserialize`.
+///
+/// The attribute `#[sthe(...)]` of struct is the option of root, selecting `body` by default,
+/// and the ones of fields are the nested options keyed by the field names, which are required
+/// to give `selector` or `xpath`. The keys are the
+/// same as `ExtractOpt`, e.g. `#[sthe(selector = "h2", target = "text", regex = "...")]`,
+/// and `nested` takes the nested options from the type of field which also derives `Extract`.
+///
+/// ```ignore
+/// #[derive(Deserialize, Extract)]
+/// #[sthe(selector = ".product")]
+/// struct Product {
+///     #[sthe(selector = "h2", target = "text")]
+///     name: String,
+///     #[sthe(selector = ".price", target = "text", type = "float")]
+///     price: f64,
+/// }
+///
+/// let product = Product::extract(html)?;
+/// ```
+#[proc_macro_derive(Extract, attributes(sthe))]
+pub fn derive_extract(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match derive(input) {
+        Ok(output) => output.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Build the option checked at compile time, and expand to the `&'static ExtractOptCompiled`
+/// compiled once.
+///
+/// The option is written like the inline tables of TOML separated by commas, the nested
+/// options are the tables keyed by other names or in `items`. The selectors, the regexes
+/// including the patterns of transforms, the names like types and kinds, and the rules across
+/// the keywords are checked while building, and the others like `xpath` and `json_path` are
+/// checked by `compile()`, which panics at the first use.
+///
+/// ```ignore
+/// let opt = sthe! {
+///     selector = ".product",
+///     title = { selector = "h2", target = "text" },
+///     price = { selector = ".price", target = "text", regex = r"\d+(\.\d+)?", type = "float" },
+/// };
+/// let extract = extract_document(html, opt);
+/// ```
+#[proc_macro]
+pub fn sthe(input: TokenStream) -> TokenStream {
+    let table::Opt(opt) = parse_macro_input!(input as table::Opt);
+    let json = serde_json::Value::Object(opt).to_string();
+    quote! {
+        {
+            static OPT: ::std::sync::OnceLock<::sthe::ExtractOptCompiled> =
+                ::std::sync::OnceLock::new();
+            OPT.get_or_init(|| {
+                let opt: ::sthe::ExtractOpt = ::sthe::__private::serde_json::from_str(#json)
+                    .expect("invalid option of `sthe!`");
+                opt.compile().expect("invalid option of `sthe!`")
+            })
+        }
+    }
+    .into()
+}
+
+fn derive(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`Extract` does not support generics",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "`Extract` requires the named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`Extract` only supports struct",
+            ))
+        }
+    };
+
+    let mut root = opt::Attrs::parse(&input.attrs)?;
+    if root.nested {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`nested` is only available for fields",
+        ));
+    }
+    if !root.json.contains_key("selector") && !root.json.contains_key("xpath") {
+        root.json.insert("selector".to_owned(), "body".into());
+    }
+    let keys: Vec<_> = fields
+        .iter()
+        .map(|x| {
+            x.ident
+                .as_ref()
+                .unwrap()
+                .to_string()
+                .trim_start_matches("r#")
+                .to_owned()
+        })
+        .collect();
+    table::check(&root.json, &keys, input.ident.span())?;
+    let root_json = serde_json::Value::Object(root.json).to_string();
+
+    let mut items = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let key = ident.to_string().trim_start_matches("r#").to_owned();
+        let attrs = opt::Attrs::parse(&field.attrs)?;
+        if !attrs.json.contains_key("selector") && !attrs.json.contains_key("xpath") {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "the field requires `selector` or `xpath` in `#[sthe(...)]`",
+            ));
+        }
+        table::check(&attrs.json, [], ident.span())?;
+        let json = serde_json::Value::Object(attrs.json).to_string();
+        let nested = if attrs.nested {
+            let ty = opt::inner_type(&field.ty);
+            quote! { item.items = <#ty as ::sthe::FromHtml>::extract_opt().items; }
+        } else {
+            quote! {}
+        };
+        items.push(quote! {
+            let mut item: ::sthe::ExtractOpt = ::sthe::__private::serde_json::from_str(#json)
+                .expect(concat!("invalid option of field `", #key, "`"));
+            #nested
+            opt.items.insert(#key.to_owned(), item);
+        });
+    }
+
+    let name = &input.ident;
+    Ok(quote! {
+        impl ::sthe::FromHtml for #name {
+            fn extract_opt() -> ::sthe::ExtractOpt {
+                let mut opt: ::sthe::ExtractOpt = ::sthe::__private::serde_json::from_str(#root_json)
+                    .expect(concat!("invalid option of `", stringify!(#name), "`"));
+                #(#items)*
+                opt
+            }
+
+            fn compiled() -> &'static ::sthe::ExtractOptCompiled {
+                static OPT: ::std::sync::OnceLock<::sthe::ExtractOptCompiled> =
+                    ::std::sync::OnceLock::new();
+                OPT.get_or_init(|| {
+                    <Self as ::sthe::FromHtml>::extract_opt()
+                        .compile()
+                        .expect(concat!("invalid option of `", stringify!(#name), "`"))
+                })
+            }
+        }
+    })
+}
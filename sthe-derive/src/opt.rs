@@ -0,0 +1,90 @@
+use crate::table;
+use serde_json::{Map, Value};
+use syn::{Attribute, Expr, ExprLit, ExprUnary, GenericArgument, Lit, PathArguments, Type, UnOp};
+
+/// The option written in `#[sthe(...)]`
+#[derive(Default)]
+pub struct Attrs {
+    /// The keys and values of `ExtractOpt`
+    pub json: Map<String, Value>,
+    /// Take the nested options from the type of field
+    pub nested: bool,
+}
+
+impl Attrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut out = Attrs::default();
+        for attr in attrs.iter().filter(|x| x.path().is_ident("sthe")) {
+            attr.parse_nested_meta(|meta| {
+                let key = match meta.path.get_ident() {
+                    Some(ident) => ident.to_string(),
+                    None => return Err(meta.error("expect a keyword")),
+                };
+                if key == "nested" {
+                    out.nested = true;
+                    return Ok(());
+                }
+                let key = match key.trim_start_matches("r#") {
+                    "ty" => "type".to_owned(),
+                    key => key.to_owned(),
+                };
+                let input = meta.value()?;
+                let span = input.span();
+                let value = value(&input.parse::<Expr>()?)?;
+                table::validate(&key, &value, span)?;
+                if out.json.insert(key.clone(), value).is_some() {
+                    return Err(meta.error(format!("duplicated keyword `{}`", key)));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+/// The value of literal, or the list of literals.
+pub fn value(expr: &Expr) -> syn::Result<Value> {
+    Ok(match expr {
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Str(x) => Value::String(x.value()),
+            Lit::Bool(x) => Value::Bool(x.value),
+            Lit::Int(x) => Value::from(x.base10_parse::<i64>()?),
+            Lit::Float(x) => Value::from(x.base10_parse::<f64>()?),
+            lit => return Err(syn::Error::new_spanned(lit, "unsupported literal")),
+        },
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => match value(expr)? {
+            Value::Number(x) if x.is_i64() => Value::from(-x.as_i64().unwrap()),
+            Value::Number(x) => Value::from(-x.as_f64().unwrap()),
+            _ => return Err(syn::Error::new_spanned(expr, "expect a number")),
+        },
+        Expr::Array(array) => {
+            Value::Array(array.elems.iter().map(value).collect::<syn::Result<_>>()?)
+        }
+        expr => {
+            return Err(syn::Error::new_spanned(
+                expr,
+                "expect a literal or a list of literals",
+            ))
+        }
+    })
+}
+
+/// The type in `Vec`, `Option` and `Box`.
+pub fn inner_type(ty: &Type) -> &Type {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last() {
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if matches!(segment.ident.to_string().as_str(), "Vec" | "Option" | "Box") {
+                    if let Some(GenericArgument::Type(ty)) = args.args.first() {
+                        return inner_type(ty);
+                    }
+                }
+            }
+        }
+    }
+    ty
+}
@@ -0,0 +1,435 @@
+use crate::opt;
+use proc_macro2::Span;
+use serde_json::{Map, Value};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::{braced, bracketed, token, Expr, Ident, LitStr, Token};
+
+/// The keywords of `ExtractOpt`, the other keys of option are the nested options
+const KEYWORDS: &[&str] = &[
+    "kind",
+    "table_rename",
+    "table_transform",
+    "links_dedup",
+    "links_canonicalize",
+    "target",
+    "selector",
+    "xpath",
+    "text_separator",
+    "transform",
+    "regex",
+    "regex_mode",
+    "regex_flags",
+    "regex_replace",
+    "json_path",
+    "parse_as",
+    "resolve_url",
+    "type",
+    "format",
+    "many",
+    "pick",
+    "required",
+    "default",
+    "items",
+    "fields",
+];
+
+/// The types of `type`
+const TYPES: &[&str] = &[
+    "string", "int", "float", "bool", "decimal", "datetime", "json",
+];
+
+/// The kinds of `kind`
+const KINDS: &[&str] = &[
+    "element",
+    "table",
+    "json_ld",
+    "microdata",
+    "rdfa",
+    "opengraph",
+    "twitter_card",
+    "form",
+    "links",
+];
+
+/// The modes of `regex_mode`
+const REGEX_MODES: &[&str] = &["first", "all", "replace", "filter"];
+
+/// The flags of `regex_flags`
+const REGEX_FLAGS: &[&str] = &["case_insensitive", "multiline", "dot_all"];
+
+/// Check the value of keyword, i.e. the selector, the regexes including the patterns of
+/// transforms, the type, the kind, the regex mode and flags, and the pick.
+pub fn validate(key: &str, value: &Value, span: Span) -> syn::Result<()> {
+    let error = |msg: String| Err(syn::Error::new(span, msg));
+    let regex = |text: &str| match regex::Regex::new(text) {
+        Ok(_) => Ok(()),
+        Err(e) => error(format!("invalid regex: {}", e)),
+    };
+    match (key, value) {
+        ("selector", Value::Array(values)) => {
+            values.iter().try_for_each(|x| validate(key, x, span))
+        }
+        ("regex_flags", Value::Array(flags)) => flags.iter().try_for_each(|flag| match flag {
+            Value::String(flag) if REGEX_FLAGS.contains(&flag.as_str()) => Ok(()),
+            Value::String(flag) => error(format!("unknown regex flag `{}`", flag)),
+            _ => error("`regex_flags` should be a list of strings".to_owned()),
+        }),
+        ("selector", Value::String(text)) => match scraper::Selector::parse(text) {
+            Ok(_) => Ok(()),
+            Err(e) => error(format!("invalid selector `{}`: {:?}", text, e)),
+        },
+        ("regex", Value::String(text)) => regex(text),
+        ("transform", Value::Array(steps)) => steps
+            .iter()
+            .filter_map(|x| x.get("replace")?.get("pattern")?.as_str())
+            .try_for_each(regex),
+        ("table_transform", Value::Object(columns)) => columns
+            .values()
+            .try_for_each(|x| validate("transform", x, span)),
+        ("type", Value::String(ty)) if !TYPES.contains(&ty.as_str()) => {
+            error(format!("unknown type `{}`", ty))
+        }
+        ("kind", Value::String(kind)) if !KINDS.contains(&kind.as_str()) => {
+            error(format!("unknown kind `{}`", kind))
+        }
+        ("regex_mode", Value::String(mode)) if !REGEX_MODES.contains(&mode.as_str()) => {
+            error(format!("unknown regex mode `{}`", mode))
+        }
+        ("pick", Value::String(pick)) if !matches!(pick.as_str(), "first" | "last" | "all") => {
+            error(format!("unknown pick `{}`", pick))
+        }
+        ("pick", Value::Number(index)) if !index.is_u64() => {
+            error("`pick` should be an index from 0".to_owned())
+        }
+        ("type" | "kind" | "regex_mode" | "pick", Value::String(_))
+        | ("pick", Value::Number(_)) => Ok(()),
+        ("selector" | "regex" | "type" | "kind" | "regex_mode", _) => {
+            error(format!("`{}` should be a string", key))
+        }
+        ("regex_flags", _) => error("`regex_flags` should be a list of strings".to_owned()),
+        ("transform", _) => error("`transform` should be a list".to_owned()),
+        ("table_transform", _) => error("`table_transform` should be a table".to_owned()),
+        ("pick", _) => error("`pick` should be a string or an index".to_owned()),
+        _ => Ok(()),
+    }
+}
+
+/// Check the rules across the keywords of option like `compile()`, `items` are the keys of
+/// the nested options.
+pub fn check<'a>(
+    opt: &Map<String, Value>,
+    items: impl IntoIterator<Item = &'a String>,
+    span: Span,
+) -> syn::Result<()> {
+    let error = |msg: &str| Err(syn::Error::new(span, msg));
+    let has = |key: &str| opt.get(key).is_some_and(|x| !x.is_null());
+    let is = |key: &str, value: &str| opt.get(key).and_then(Value::as_str) == Some(value);
+    let kind = opt.get("kind").and_then(Value::as_str).unwrap_or("element");
+    let non_empty = |key: &str| match opt.get(key) {
+        Some(Value::Array(x)) => !x.is_empty(),
+        Some(Value::Object(x)) => !x.is_empty(),
+        Some(Value::Bool(x)) => *x,
+        Some(Value::Null) | None => false,
+        Some(_) => true,
+    };
+    let items: Vec<_> = items.into_iter().collect();
+
+    if opt.get("required") == Some(&Value::Bool(true)) && has("default") {
+        return error("`required` conflicts with `default`");
+    }
+    match (has("selector"), has("xpath")) {
+        (true, true) => return error("`selector` and `xpath` are exclusive"),
+        (false, false) => return error("either `selector` or `xpath` is required"),
+        _ => {}
+    }
+    if kind != "element" && (non_empty("target") || has("regex") || !items.is_empty()) {
+        return error("only kind `element` accepts `target`, `regex` or nested options");
+    }
+    if kind != "element" && (has("type") || has("json_path") || has("text_separator")) {
+        return error("only kind `element` accepts `type`, `json_path` or `text_separator`");
+    }
+    if !matches!(kind, "element" | "table") && non_empty("transform") {
+        return error("only kind `element` and `table` accept `transform`");
+    }
+    if has("parse_as") && !non_empty("target") {
+        return error("`parse_as` requires `target`");
+    }
+    if kind != "table" && (non_empty("table_rename") || non_empty("table_transform")) {
+        return error("`table_rename` and `table_transform` are only available for kind `table`");
+    }
+    if kind != "links" && (non_empty("links_dedup") || non_empty("links_canonicalize")) {
+        return error("`links_dedup` and `links_canonicalize` are only available for kind `links`");
+    }
+    match opt.get("regex").and_then(Value::as_str) {
+        Some(_) if is("regex_mode", "replace") && !has("regex_replace") => {
+            return error("`regex_replace` is required by mode `replace`")
+        }
+        Some(_) if !is("regex_mode", "replace") && has("regex_replace") => {
+            return error("`regex_replace` is only available for mode `replace`")
+        }
+        Some(regex) => {
+            let names = regex::Regex::new(regex).map_or(vec![], |x| {
+                x.capture_names().flatten().map(str::to_owned).collect()
+            });
+            if let Some(name) = names.iter().find(|x| items.contains(x)) {
+                let msg = format!(
+                    "the named capture `{}` conflicts with the nested option",
+                    name
+                );
+                return error(&msg);
+            }
+        }
+        None if (has("regex_mode") && !is("regex_mode", "first"))
+            || non_empty("regex_flags")
+            || has("regex_replace") =>
+        {
+            return error("`regex_mode`, `regex_flags` and `regex_replace` require `regex`")
+        }
+        None => {}
+    }
+    if let (Some(many), Some(pick)) = (opt.get("many").and_then(Value::as_bool), opt.get("pick")) {
+        if many != (pick.as_str() == Some("all")) {
+            return error("`many` conflicts with `pick`");
+        }
+    }
+    Ok(())
+}
+
+/// The key of table, an identifier including keywords like `type`, or a string
+fn key(input: ParseStream) -> syn::Result<String> {
+    if input.peek(LitStr) {
+        Ok(input.parse::<LitStr>()?.value())
+    } else {
+        Ok(Ident::parse_any(input)?.unraw().to_string())
+    }
+}
+
+/// The value of table, a literal, a list `[...]` or a table `{...}`.
+fn value(input: ParseStream) -> syn::Result<Value> {
+    if input.peek(token::Brace) {
+        let content;
+        braced!(content in input);
+        return Ok(Value::Object(entries(&content, |input, _| value(input))?));
+    }
+    if input.peek(token::Bracket) {
+        let content;
+        bracketed!(content in input);
+        let mut values = vec![];
+        while !content.is_empty() {
+            values.push(value(&content)?);
+            if content.is_empty() {
+                break;
+            }
+            content.parse::<Token![,]>()?;
+        }
+        return Ok(Value::Array(values));
+    }
+    opt::value(&input.parse::<Expr>()?)
+}
+
+/// The option `{...}`, whose keywords are checked.
+fn option(input: ParseStream) -> syn::Result<Value> {
+    if !input.peek(token::Brace) {
+        return Err(input.error("expect the nested option `{ ... }`"));
+    }
+    let content;
+    braced!(content in input);
+    Ok(Value::Object(options(&content)?))
+}
+
+/// The entries of option, the values of `items`, `fields` and the other keys are options.
+fn options(input: ParseStream) -> syn::Result<Map<String, Value>> {
+    let span = input.span();
+    let map = entries(input, |input, key| {
+        let span = input.span();
+        let value = match key {
+            "items" | "fields" => {
+                if !input.peek(token::Brace) {
+                    return Err(input.error(format!("`{}` should be the table of options", key)));
+                }
+                let content;
+                braced!(content in input);
+                Value::Object(entries(&content, |input, _| option(input))?)
+            }
+            key if !KEYWORDS.contains(&key) => option(input)?,
+            key => {
+                let value = value(input)?;
+                validate(key, &value, span)?;
+                value
+            }
+        };
+        Ok(value)
+    })?;
+    let explicit = map.contains_key("items") || map.contains_key("fields");
+    if let Some(key) = map
+        .keys()
+        .find(|x| explicit && !KEYWORDS.contains(&x.as_str()))
+    {
+        let msg = format!(
+            "unknown keyword `{}`, the nested options should be in `items` or `fields` if given",
+            key
+        );
+        return Err(syn::Error::new(span, msg));
+    }
+    let items = match map.get("items").or_else(|| map.get("fields")) {
+        Some(Value::Object(items)) => items.keys().collect(),
+        _ => map
+            .keys()
+            .filter(|x| !KEYWORDS.contains(&x.as_str()))
+            .collect::<Vec<_>>(),
+    };
+    check(&map, items, span)?;
+    Ok(map)
+}
+
+/// The entries `key = value` separated by commas.
+fn entries(
+    input: ParseStream,
+    value: impl Fn(ParseStream, &str) -> syn::Result<Value>,
+) -> syn::Result<Map<String, Value>> {
+    let mut map = Map::new();
+    while !input.is_empty() {
+        let span = input.span();
+        let key = key(input)?;
+        input.parse::<Token![=]>()?;
+        let value = value(input, &key)?;
+        if map.insert(key.clone(), value).is_some() {
+            return Err(syn::Error::new(span, format!("duplicated key `{}`", key)));
+        }
+        if input.is_empty() {
+            break;
+        }
+        input.parse::<Token![,]>()?;
+    }
+    Ok(map)
+}
+
+/// The option written in `sthe! { ... }`
+pub struct Opt(pub Map<String, Value>);
+
+impl Parse for Opt {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(Opt(options(input)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The keywords should be the fields of `ExtractOpt` by their serde names, with `items`
+    /// and `fields` instead of the flattened `items`.
+    #[test]
+    fn test_keywords() {
+        let file = syn::parse_file(include_str!("../../src/lib.rs")).unwrap();
+        let opt = file
+            .items
+            .iter()
+            .find_map(|x| match x {
+                syn::Item::Struct(x) if x.ident == "ExtractOpt" => Some(x),
+                _ => None,
+            })
+            .unwrap();
+        let mut fields = vec!["items".to_owned(), "fields".to_owned()];
+        for field in opt.fields.iter() {
+            let mut name = field.ident.as_ref().unwrap().to_string();
+            let mut flatten = false;
+            for attr in field.attrs.iter().filter(|x| x.path().is_ident("serde")) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        name = meta.value()?.parse::<LitStr>()?.value();
+                    } else if meta.path.is_ident("flatten") {
+                        flatten = true;
+                    } else if meta.input.peek(Token![=]) {
+                        meta.value()?.parse::<Expr>()?;
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            }
+            if !flatten {
+                fields.push(name);
+            }
+        }
+        let mut keywords: Vec<_> = KEYWORDS.iter().map(|x| x.to_string()).collect();
+        fields.sort();
+        keywords.sort();
+        assert_eq!(fields, keywords);
+    }
+
+    #[test]
+    fn test_check() {
+        let parse = |input: &str| syn::parse_str::<Opt>(input).map(|_| ());
+        let error = |input: &str| parse(input).unwrap_err().to_string();
+
+        assert!(parse(
+            r#"selector = "ul", items = { x = { selector = "li", regex = "(?P<y>\\d)" } }"#
+        )
+        .is_ok());
+        assert_eq!(
+            error(r#"selector = "p", transform = [{ replace = { pattern = "(" } }]"#)
+                .split(':')
+                .next(),
+            Some("invalid regex")
+        );
+        assert_eq!(
+            error(r#"kind = "table", selector = "table", table_transform = { a = [{ replace = { pattern = "[" } }] }"#)
+                .split(':')
+                .next(),
+            Some("invalid regex")
+        );
+        assert_eq!(
+            error(r#"selector = "p", kind = "tables""#),
+            "unknown kind `tables`"
+        );
+        assert_eq!(
+            error(r#"selector = "p", regex = "a", regex_mode = "any""#),
+            "unknown regex mode `any`"
+        );
+        assert_eq!(
+            error(r#"selector = "p", regex = "a", regex_flags = ["dotall"]"#),
+            "unknown regex flag `dotall`"
+        );
+        assert_eq!(
+            error(r#"selector = "p", regex = "a", regex_mode = "replace""#),
+            "`regex_replace` is required by mode `replace`"
+        );
+        assert_eq!(
+            error(r#"selector = "p", regex = "a", regex_replace = "b""#),
+            "`regex_replace` is only available for mode `replace`"
+        );
+        assert_eq!(
+            error(r#"selector = "p", regex_flags = ["dot_all"]"#),
+            "`regex_mode`, `regex_flags` and `regex_replace` require `regex`"
+        );
+        assert_eq!(
+            error(r#"selector = "p", xpath = "//p""#),
+            "`selector` and `xpath` are exclusive"
+        );
+        assert_eq!(
+            error(r#"selector = "p", x = { target = "text" }"#),
+            "either `selector` or `xpath` is required"
+        );
+        assert_eq!(
+            error(r#"selector = "p", required = true, default = "a""#),
+            "`required` conflicts with `default`"
+        );
+        assert_eq!(
+            error(r#"selector = "p", regex = "(?P<x>a)", x = { selector = "a" }"#),
+            "the named capture `x` conflicts with the nested option"
+        );
+        assert_eq!(
+            error(r#"selector = "p", kind = "links", links_dedup = true, type = "int""#),
+            "only kind `element` accepts `type`, `json_path` or `text_separator`"
+        );
+        assert_eq!(
+            error(r#"selector = "p", links_dedup = true"#),
+            "`links_dedup` and `links_canonicalize` are only available for kind `links`"
+        );
+        assert_eq!(
+            error(r#"selector = "p", many = false, pick = "all""#),
+            "`many` conflicts with `pick`"
+        );
+    }
+}
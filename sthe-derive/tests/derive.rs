@@ -0,0 +1,74 @@
+use serde::Deserialize;
+use sthe::{Extract, FromHtml};
+
+#[derive(Deserialize, Extract, Debug, PartialEq)]
+struct Offer {
+    #[sthe(selector = ".price", target = "text", type = "float")]
+    price: f64,
+    #[sthe(selector = ".seller", target = "text", regex = r"by (\w+)")]
+    seller: String,
+}
+
+#[derive(Deserialize, Extract, Debug, PartialEq)]
+#[sthe(selector = ".product")]
+struct Product {
+    #[sthe(selector = "h2", target = "text")]
+    name: String,
+    #[sthe(selector = "li", target = "text", many = true)]
+    tags: Vec<String>,
+    #[sthe(selector = ".offer", nested)]
+    offers: Vec<Offer>,
+    #[sthe(selector = ".missing", target = "text")]
+    note: Option<String>,
+}
+
+#[test]
+fn test_derive() {
+    let html = r#"
+<div class="product">
+    <h2>Shoe</h2>
+    <ul><li>new</li><li>sale</li></ul>
+    <div class="offer"><span class="price">9.5</span><span class="seller">by alice</span></div>
+    <div class="offer"><span class="price">8</span><span class="seller">by bob</span></div>
+</div>
+    "#;
+    let product = Product::extract(html).unwrap();
+    assert_eq!(
+        product,
+        Product {
+            name: "Shoe".to_owned(),
+            tags: vec!["new".to_owned(), "sale".to_owned()],
+            offers: vec![
+                Offer {
+                    price: 9.5,
+                    seller: "alice".to_owned(),
+                },
+                Offer {
+                    price: 8.0,
+                    seller: "bob".to_owned(),
+                },
+            ],
+            note: None,
+        }
+    );
+    assert!(std::ptr::eq(Product::compiled(), Product::compiled()));
+}
+
+#[derive(Deserialize, Extract, Debug, PartialEq)]
+#[sthe(xpath = "//article[@id]")]
+struct Article {
+    #[sthe(xpath = "./h1", target = "text")]
+    title: String,
+}
+
+#[test]
+fn test_derive_xpath() {
+    let html = r#"<article><h1>Draft</h1></article><article id="a"><h1>Hello</h1></article>"#;
+    let article = Article::extract(html).unwrap();
+    assert_eq!(
+        article,
+        Article {
+            title: "Hello".to_owned(),
+        }
+    );
+}
@@ -0,0 +1,35 @@
+use sthe::{extract_fragment, sthe};
+
+#[test]
+fn test_sthe() {
+    let opt = sthe! {
+        selector = ".product",
+        items = {
+            name = { selector = "h2", target = "text" },
+            price = { selector = ".price", target = "text", regex = r"(\d+\.\d+)", type = "float" },
+            type = { selector = ".type", target = "text", transform = ["uppercase"] },
+        },
+    };
+    let extract = extract_fragment(
+        r#"<div class="product"><h2>Shoe</h2><span class="price">$9.50</span><i class="type">boot</i></div>"#,
+        opt,
+    );
+    assert_eq!(
+        serde_json::to_string(&extract).unwrap(),
+        r#"{"name":{"text":"Shoe"},"price":{"text":9.5},"type":{"text":"BOOT"}}"#
+    );
+}
+
+#[test]
+fn test_sthe_order() {
+    let opt = sthe! {
+        selector = "p",
+        zeta = { selector = "b", target = "text" },
+        alpha = { selector = "i", target = "text" },
+    };
+    let extract = extract_fragment("<p><i>a</i><b>z</b></p>", opt);
+    assert_eq!(
+        serde_json::to_string(&extract).unwrap(),
+        r#"{"zeta":{"text":"z"},"alpha":{"text":"a"}}"#
+    );
+}